@@ -0,0 +1,120 @@
+use std::env;
+
+use anyhow::Result;
+use dotenvy::dotenv;
+use futures::StreamExt;
+use rymo::extract::{Query, Rejection};
+use rymo::form::{Form, FormMap};
+use rymo::http::method::Method;
+use rymo::http::request::Request;
+use rymo::http::response::{IntoResponse, Status};
+use rymo::multipart::{Limits, Multipart, PartData};
+use rymo::{Route, Rymo};
+use serde::Deserialize;
+use tracing::{info, warn};
+use tracing_subscriber::{fmt, layer::SubscriberExt, registry, util::SubscriberInitExt, EnvFilter};
+
+pub fn init_logger() {
+    let formatting_layer = fmt::layer()
+        // .pretty()
+        .with_thread_ids(false)
+        .with_target(false)
+        .with_writer(std::io::stdout);
+
+    let env_layer = EnvFilter::try_from_env("RYMO_LOG").unwrap_or_else(|_| "info".into());
+
+    registry().with(env_layer).with(formatting_layer).init();
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_logger();
+    dotenv().map_err(|err| warn!("env file {err}")).ok();
+
+    let port = env::var("PORT").unwrap_or("4000".into());
+    info!("listening on {port}");
+    let app = Rymo::new(&port);
+
+    app.get("/search", search).await;
+    app.post("/signup", signup).await;
+    app.post("/raw", raw).await;
+    // multipart handlers parse parts as they arrive, the body is not read first
+    app.post("/upload", upload).await;
+    app.register(Route::new(Method::Post, "/count", count).stream_body())
+        .await?;
+    app.serve().await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Signup {
+    name: String,
+    age: u8,
+    /// Every `tags=..` of the form
+    #[serde(default)]
+    tags: Vec<String>,
+    newsletter: Option<bool>,
+}
+
+/// `GET /search?q=hello+world`
+async fn search(query: Query) -> impl IntoResponse {
+    match query.get("q") {
+        Some(q) => (Status::Ok, format!("searching {q}")),
+        None => (Status::BadRequest, "missing q".to_owned()),
+    }
+}
+
+/// Broken encoding is 400, form without `name` or `age` is 422
+async fn signup(Form(signup): Form<Signup>) -> impl IntoResponse {
+    info!("signup {signup:?}");
+    (Status::Created, format!("welcome {}", signup.name))
+}
+
+/// Echo the pairs line by line
+async fn raw(form: FormMap) -> impl IntoResponse {
+    form.iter()
+        .map(|(k, v)| format!("{k}={v}\n"))
+        .collect::<String>()
+}
+
+/// Text fields stay in memory, files larger than 1 KiB are spooled to disk
+async fn upload(multipart: Multipart) -> Result<String, Rejection> {
+    let mut multipart = multipart.with_limits(Limits {
+        field_size: 1024 * 1024,
+        memory_size: 1024,
+        ..Default::default()
+    });
+    let mut summary = String::new();
+    while let Some(field) = multipart.next_field().await? {
+        let part = field.spool().await?;
+        let name = part.name.unwrap_or_default();
+        let line = match (&part.data, part.file_name) {
+            (PartData::Memory(bytes), None) => {
+                format!("{name}: {}\n", String::from_utf8_lossy(bytes))
+            }
+            (PartData::Memory(bytes), Some(file)) => {
+                format!("{name}: {file} {} bytes in memory\n", bytes.len())
+            }
+            (PartData::File(temp), Some(file)) => {
+                format!("{name}: {file} {} bytes spooled\n", temp.size())
+            }
+            (PartData::File(temp), None) => format!("{name}: {} bytes spooled\n", temp.size()),
+        };
+        summary.push_str(&line);
+    }
+    Ok(summary)
+}
+
+/// Count bytes of body without holding it in memory
+async fn count(mut req: Request) -> Result<String, Rejection> {
+    let Some(mut body) = req.take_body_stream() else {
+        return Ok("0".to_owned());
+    };
+    let mut len = 0;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| Rejection::bad_request(e.to_string()))?;
+        len += chunk.len();
+    }
+    Ok(len.to_string())
+}
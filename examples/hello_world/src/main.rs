@@ -1,13 +1,18 @@
 use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use anyhow::{Ok, Result};
 use dotenvy::dotenv;
 use tracing::{info, warn};
 use tracing_subscriber::{fmt, prelude::*, registry, EnvFilter};
 
+use rymo::extract::{Extension, Path, PeerAddr, State};
+use rymo::http::method::Method;
 use rymo::http::request::Request;
-use rymo::http::response::{Response, Status};
-use rymo::Rymo;
+use rymo::http::response::{IntoResponse, Response, Status};
+use rymo::middleware::{from_fn, Next};
+use rymo::{Route, Router, Rymo};
 
 pub fn init_logger() {
     let formatting_layer = fmt::layer()
@@ -28,19 +33,110 @@ async fn main() -> Result<()> {
 
     let port = env::var("PORT").unwrap_or("4000".into());
     info!("listening on {port}");
-    let app = Rymo::new(&port);
+    let app = Rymo::new(&port).with_state(AppState {
+        name: "Rymo".to_owned(),
+        visits: Arc::default(),
+    });
+    app.layer(from_fn("logger", logger)).await;
 
     app.get("/", handler).await;
     app.post("/", handler).await;
+    app.route(Method::extension("PURGE")?, "/", handler).await;
+
+    let api = Router::new()
+        .get("/", handler)
+        .get("/hello", handler)
+        .register(Route::new(Method::Get, "/users/:id", user).name("user"))?
+        .not_found(not_found);
+    app.nest("/api/v1", api).await?;
+
+    app.get("/visits", visits).await;
+    app.get("/request-id", request_id).await;
+    app.get("/ip", ip).await;
+    // the counter router only sees the counter of app state
+    let counter = Router::new().get("/", count);
+    app.nest_with_state("/counter", counter, |s: &AppState| s.visits.clone())
+        .await?;
+
+    let internal = Router::new().get("/", internal);
+    app.host("*.internal", internal).await?;
+    // toggle `GET /flag` while serving
+    let handle = app.handle();
+    app.post("/flag", move || {
+        let handle = handle.clone();
+        async move {
+            let flag = Route::new(Method::Get, "/flag", handler);
+            if handle.add(flag).is_err() {
+                handle.remove(&Method::Get, "/flag")?;
+            }
+            Ok(Status::NoContent)
+        }
+    })
+    .await;
+
+    info!("routes\n{}", app.route_table().await);
     app.serve().await?;
     Ok(())
 }
 
-async fn handler(_req: Request, mut res: Response) -> Result<Response> {
-    res.status = Status::Ok;
-    res.headers
-        .entry("Content-Type".to_owned())
-        .or_insert("text/plain".to_owned());
-    res.body = String::from("Hello Rymo!").into();
+#[derive(Clone)]
+struct AppState {
+    name: String,
+    visits: Arc<AtomicU64>,
+}
+
+/// Id of the request, inserted by logger middleware
+#[derive(Debug, Clone, Copy)]
+struct RequestId(u64);
+
+async fn logger(mut req: Request, res: Response, next: Next) -> Result<Response> {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let id = RequestId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    req.extensions.insert(id);
+    let (method, path) = (req.method.clone(), req.uri.to_string());
+    let peer = req.peer_addr.map(|a| a.to_string()).unwrap_or_default();
+    let res = next.run(req, res).await?;
+    info!("{id:?} {peer} {method} {path} {}", res.status);
     Ok(res)
 }
+
+async fn handler(req: Request) -> impl IntoResponse {
+    info!(
+        "{} {} nested at {:?}",
+        req.method,
+        req.path(),
+        req.nest_path
+    );
+    "Hello Rymo!"
+}
+
+async fn not_found(req: Request) -> impl IntoResponse {
+    (Status::NotFound, format!("{} not found", req.path()))
+}
+
+async fn internal(req: Request) -> String {
+    format!("Hello {}!", req.host().unwrap_or("internal"))
+}
+
+async fn user(Path(id): Path<u64>, req: Request) -> Result<String> {
+    let next = (id + 1).to_string();
+    let url = req.url_for("user", &[("id", &next)], &[("from", &id.to_string())])?;
+    Ok(format!("user {id}, next {url}"))
+}
+
+async fn visits(State(state): State<AppState>) -> String {
+    let visits = state.visits.fetch_add(1, Ordering::Relaxed) + 1;
+    format!("{} visited {visits} times", state.name)
+}
+
+async fn count(State(visits): State<Arc<AtomicU64>>) -> String {
+    visits.load(Ordering::Relaxed).to_string()
+}
+
+async fn request_id(Extension(RequestId(id)): Extension<RequestId>) -> String {
+    format!("request {id}")
+}
+
+async fn ip(PeerAddr(addr): PeerAddr) -> String {
+    addr.ip().to_string()
+}
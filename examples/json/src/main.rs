@@ -2,9 +2,14 @@ use std::env;
 
 use anyhow::{Ok, Result};
 use dotenvy::dotenv;
+use futures::{stream, StreamExt};
+use rymo::guard;
+use rymo::http::method::Method;
 use rymo::http::request::Request;
-use rymo::http::response::Response;
-use rymo::Rymo;
+use rymo::http::response::{IntoResponse, Status};
+use rymo::json::{Json, NdJson};
+use rymo::{Route, Rymo};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::{info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, registry, util::SubscriberInitExt, EnvFilter};
@@ -31,20 +36,36 @@ async fn main() -> Result<()> {
     let app = Rymo::new(&port);
 
     app.get("/", handler).await;
-    app.post("/", handler).await;
+    let post = Route::new(Method::Post, "/", echo).guard(guard::content_type("application/json"));
+    app.register(post).await?;
+    app.get("/numbers", numbers).await;
     app.serve().await?;
     Ok(())
 }
 
-async fn handler(req: Request, res: Response) -> Result<Response> {
-    let unknown = "Unknown".to_owned();
-    let host = req.headers.get("host").unwrap_or(&unknown);
+#[derive(Debug, Serialize, Deserialize)]
+struct Message {
+    status: String,
+    message: String,
+}
+
+async fn handler(req: Request) -> impl IntoResponse {
+    let host = req.host().unwrap_or("Unknown");
     info!("handle request from {host}",);
 
-    let _body = json!({
+    Json(json!({
         "status": "ok",
         "message": "hello world"
-    });
-    // Response(http::Status::Ok, body.to_string().into())
-    Ok(res)
+    }))
+}
+
+/// Malformed JSON is 400, JSON without `status` or `message` is 422
+async fn echo(Json(message): Json<Message>) -> impl IntoResponse {
+    info!("echo {message:?}");
+    (Status::Created, Json(message))
+}
+
+/// Stream a large result set line by line
+async fn numbers() -> impl IntoResponse {
+    NdJson(stream::iter(0..1000).map(|n| json!({ "n": n, "square": n * n })))
 }
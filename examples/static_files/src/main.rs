@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use anyhow::{Ok, Result};
 use dotenvy::dotenv;
 use rymo::static_handler;
-use rymo::Rymo;
+use rymo::{Rymo, TrailingSlash};
 use tracing::{info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, registry, util::SubscriberInitExt, EnvFilter};
 
@@ -29,6 +29,7 @@ async fn main() -> Result<()> {
     let app = Rymo::new(&port);
 
     let path = env::var("STATIC").expect("static folder must be set");
+    app.trailing_slash(TrailingSlash::Redirect).await;
     app.assets("/", &PathBuf::from(path), static_handler).await;
     app.serve().await?;
     Ok(())
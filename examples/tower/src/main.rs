@@ -0,0 +1,63 @@
+use std::{convert::Infallible, env, time::Duration};
+
+use anyhow::{Ok, Result};
+use dotenvy::dotenv;
+use http_body_util::Full;
+use rymo::http::request::Request;
+use rymo::http::response::Response;
+use rymo::tower::{layer, service, ServiceBody};
+use rymo::Rymo;
+use tower::{limit::ConcurrencyLimitLayer, service_fn, timeout::TimeoutLayer};
+use tracing::{info, warn};
+use tracing_subscriber::{fmt, layer::SubscriberExt, registry, util::SubscriberInitExt, EnvFilter};
+
+pub fn init_logger() {
+    let formatting_layer = fmt::layer()
+        // .pretty()
+        .with_thread_ids(false)
+        .with_target(false)
+        .with_writer(std::io::stdout);
+
+    let env_layer = EnvFilter::try_from_env("RYMO_LOG").unwrap_or_else(|_| "info".into());
+
+    registry().with(env_layer).with(formatting_layer).init();
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_logger();
+    dotenv().map_err(|err| warn!("env file {err}")).ok();
+
+    let port = env::var("PORT").unwrap_or("4000".into());
+    info!("listening on {port}");
+    let app = Rymo::new(&port);
+
+    // tower layers wrap every route handler
+    app.layer(layer("concurrency", ConcurrencyLimitLayer::new(64)))
+        .await;
+    app.layer(layer("timeout", TimeoutLayer::new(Duration::from_secs(1))))
+        .await;
+
+    app.get("/", handler).await;
+    app.get("/slow", slow).await;
+    // a tower service as route handler
+    app.get("/service", service(service_fn(hello))).await;
+    app.serve().await?;
+    Ok(())
+}
+
+async fn handler(_req: Request, mut res: Response) -> Result<Response> {
+    res.body = "hello rymo".into();
+    Ok(res)
+}
+
+/// Timeout layer fails it with 500
+async fn slow(req: Request, res: Response) -> Result<Response> {
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    handler(req, res).await
+}
+
+async fn hello(req: http::Request<ServiceBody>) -> Result<http::Response<ServiceBody>, Infallible> {
+    let body = format!("hello tower, {} {}", req.method(), req.uri());
+    std::result::Result::Ok(http::Response::new(Full::from(body)))
+}
@@ -1,13 +1,30 @@
 use anyhow::anyhow;
 
+use crate::response::Status;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("invalid request {0}")]
     BadRequest(String),
+    /// Request body is larger than the limit, see [`crate::Rymo::body_limit`]
+    #[error("request body exceeds {0} bytes")]
+    ContentTooLarge(usize),
     #[error("server internal error {0}")]
     InternalServerError(anyhow::Error),
 }
 
+impl Error {
+    /// Status to respond
+    #[inline]
+    pub fn status(&self) -> Status {
+        match self {
+            Self::BadRequest(_) => Status::BadRequest,
+            Self::ContentTooLarge(_) => Status::ContentTooLarge,
+            Self::InternalServerError(_) => Status::InternalServer,
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Self::InternalServerError(anyhow!("{value}"))
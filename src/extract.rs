@@ -0,0 +1,427 @@
+use std::{any::type_name, fmt::Display, net::SocketAddr, str::FromStr};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use log::error;
+
+use crate::{
+    error::Error,
+    form::FormMap,
+    http::{header::HeaderMap, method::Method, uri::Uri},
+    request::Request,
+    response::{IntoResponse, Response, Status},
+    router::Params,
+};
+
+/// Extract a value from request without consuming it
+///
+/// Extractors implementing this can be any argument of a handler.
+pub trait FromRequestParts: Sized {
+    /// Response when extraction failed, the handler is not called
+    type Rejection: IntoResponse;
+
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>>;
+}
+
+/// Extract a value from request, may consume it's body
+///
+/// Extractors implementing only this must be the last argument of a
+/// handler. `M` tells apart the implementation for [`FromRequestParts`].
+pub trait FromRequest<M = ViaRequest>: Sized {
+    type Rejection: IntoResponse;
+
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>>;
+
+    /// Read the body from it's stream instead of [`Request::body`], routes
+    /// of handlers taking it stream their body, see [`Route::stream_body`]
+    ///
+    /// [`Route::stream_body`]: crate::router::Route::stream_body
+    #[inline]
+    fn streams_body() -> bool {
+        false
+    }
+}
+
+/// Marker of [`FromRequest`] that consumes the request
+pub enum ViaRequest {}
+/// Marker of [`FromRequest`] that is implemented by [`FromRequestParts`]
+pub enum ViaParts {}
+
+impl<T> FromRequest<ViaParts> for T
+where
+    T: FromRequestParts + Send + 'static,
+{
+    type Rejection = T::Rejection;
+
+    #[inline]
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move { T::from_request_parts(&req).await })
+    }
+}
+
+/// Extraction failure responded with 4xx or 5xx status
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub status: Status,
+    pub message: String,
+}
+
+impl Rejection {
+    #[inline]
+    pub fn new(status: Status, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    #[inline]
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, message)
+    }
+
+    /// Server side mistake, log `detail` and respond a plain 500 without it
+    #[inline]
+    pub fn internal_error(detail: impl Display) -> Self {
+        error!("{detail}");
+        Self::new(Status::InternalServer, Status::InternalServer.reason())
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+impl IntoResponse for Rejection {
+    #[inline]
+    fn into_response(self) -> Response {
+        (self.status, self.message).into_response()
+    }
+}
+
+/// Path parameters parsed in order of the route pattern
+///
+/// ```not_rust
+/// // GET /users/:id/posts/:post
+/// async fn post(Path((id, post)): Path<(u64, String)>) -> String {
+///     format!("{id} {post}")
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<T>(pub T);
+
+/// Value parsed from path parameters, see [`Path`]
+pub trait FromParams: Sized {
+    fn from_params(params: &Params) -> Result<Self, Rejection>;
+}
+
+impl<T: FromParams> FromRequestParts for Path<T> {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { T::from_params(&req.params).map(Path) })
+    }
+}
+
+/// Parse the value of a single path parameter
+#[inline]
+fn parse_param<T>(params: &Params, index: usize) -> Result<T, Rejection>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Some((key, value)) = params.get(index) else {
+        // route pattern has less parameters than the handler asked
+        return Err(Rejection::internal_error(format!(
+            "missing path parameter {index}"
+        )));
+    };
+    value
+        .parse()
+        .map_err(|e| Rejection::bad_request(format!("invalid path parameter {key}: {e}")))
+}
+
+macro_rules! from_params_single {
+    ($($ty:ty),+) => {
+        $(
+            impl FromParams for $ty {
+                #[inline]
+                fn from_params(params: &Params) -> Result<Self, Rejection> {
+                    parse_param(params, 0)
+                }
+            }
+        )+
+    };
+}
+from_params_single!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+macro_rules! from_params_tuple {
+    ($($ty:ident $index:tt),+) => {
+        impl<$($ty),+> FromParams for ($($ty,)+)
+        where
+            $($ty: FromStr, $ty::Err: Display,)+
+        {
+            #[inline]
+            fn from_params(params: &Params) -> Result<Self, Rejection> {
+                Ok(($(parse_param::<$ty>(params, $index)?,)+))
+            }
+        }
+    };
+}
+from_params_tuple!(T1 0);
+from_params_tuple!(T1 0, T2 1);
+from_params_tuple!(T1 0, T2 1, T3 2);
+from_params_tuple!(T1 0, T2 1, T3 2, T4 3);
+
+/// Query pairs in order, percent decoded and `+` as space
+///
+/// Broken percent-encoding is rejected with 400, see [`FormMap`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query(pub FormMap);
+
+impl Query {
+    /// The first value of `key`
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key)
+    }
+}
+
+impl FromRequestParts for Query {
+    type Rejection = Rejection;
+
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            FormMap::parse(req.uri.query().unwrap_or(""))
+                .map(Query)
+                .map_err(|e| Rejection::bad_request(format!("invalid query: {e}")))
+        })
+    }
+}
+
+/// Clone of the state shared by [`Rymo::with_state`] or
+/// [`Router::with_state`], reject with 500 when no state of type `T` is set
+///
+/// ```not_rust
+/// let app = Rymo::new(&port).with_state(AppState { db });
+///
+/// async fn users(State(state): State<AppState>) -> String {
+///     state.db.users().await
+/// }
+/// ```
+///
+/// [`Rymo::with_state`]: crate::Rymo::with_state
+/// [`Router::with_state`]: crate::router::Router::with_state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct State<T>(pub T);
+
+impl<T> FromRequestParts for State<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            req.state::<T>().cloned().map(State).ok_or_else(|| {
+                Rejection::internal_error(format!("missing state of type {}", type_name::<T>()))
+            })
+        })
+    }
+}
+
+/// Clone of a request extension inserted by middleware, reject with 500
+/// when no value of type `T` is inserted, see [`Extensions`]
+///
+/// [`Extensions`]: crate::http::extensions::Extensions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Extension<T>(pub T);
+
+impl<T> FromRequestParts for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            req.extensions
+                .get::<T>()
+                .cloned()
+                .map(Extension)
+                .ok_or_else(|| {
+                    Rejection::internal_error(format!(
+                        "missing extension of type {}",
+                        type_name::<T>()
+                    ))
+                })
+        })
+    }
+}
+
+/// Remote address of the connection, reject with 500 when the request is
+/// not read from a socket
+///
+/// It's the address of the proxy when the app is behind one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl FromRequestParts for PeerAddr {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            req.peer_addr
+                .map(PeerAddr)
+                .ok_or_else(|| Rejection::new(Status::InternalServer, "missing peer address"))
+        })
+    }
+}
+
+/// Local address that accepted the connection, reject with 500 when the
+/// request is not read from a socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalAddr(pub SocketAddr);
+
+impl FromRequestParts for LocalAddr {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            req.local_addr
+                .map(LocalAddr)
+                .ok_or_else(|| Rejection::new(Status::InternalServer, "missing local address"))
+        })
+    }
+}
+
+impl FromRequestParts for Method {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(req.method.clone()) })
+    }
+}
+
+impl FromRequestParts for Uri {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(req.uri.clone()) })
+    }
+}
+
+impl FromRequestParts for HeaderMap {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(req.headers.clone()) })
+    }
+}
+
+/// `None` when `T` rejected the request
+impl<T> FromRequestParts for Option<T>
+where
+    T: FromRequestParts + Send,
+{
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request_parts(req: &Request) -> BoxFuture<'_, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(T::from_request_parts(req).await.ok()) })
+    }
+}
+
+impl FromRequest for Request {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move { Ok(req) })
+    }
+}
+
+/// Whole body, reject with 413 when it's larger than
+/// [`Request::body_limit`] and 400 when it can't be read
+impl FromRequest for Bytes {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request(mut req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            req.read_body()
+                .await
+                .map_err(|e| match e.downcast_ref::<Error>() {
+                    Some(err) => Rejection::new(err.status(), err.to_string()),
+                    None => Rejection::bad_request(format!("read body failed {e}")),
+                })
+        })
+    }
+}
+
+/// Body as UTF-8 text, reject with 400 when it's not
+impl FromRequest for String {
+    type Rejection = Rejection;
+
+    #[inline]
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            let body = Bytes::from_request(req).await?;
+            String::from_utf8(body.to_vec())
+                .map_err(|e| Rejection::bad_request(format!("body is not UTF-8: {e}")))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn missing_state_and_extension_hide_type() {
+        let req = Request::default();
+        let rejection = State::<Vec<u8>>::from_request_parts(&req)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.status, Status::InternalServer);
+        assert_eq!(rejection.message, "Internal Server Error");
+        let rejection = Extension::<Vec<u8>>::from_request_parts(&req)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.status, Status::InternalServer);
+        assert_eq!(rejection.message, "Internal Server Error");
+    }
+
+    #[tokio::test]
+    async fn decode_query() {
+        let query = |target: &str| {
+            let head = format!("GET {target} HTTP/1.1\r\nHost: x\r\n\r\n");
+            Request::parse_from_bytes(Bytes::from(head)).unwrap()
+        };
+        let req = query("/search?q=rust+lang&tag=a&tag=b%20c");
+        let Query(map) = Query::from_request_parts(&req).await.unwrap();
+        assert_eq!(map.get("q"), Some("rust lang"));
+        assert_eq!(map.get_all("tag").collect::<Vec<_>>(), ["a", "b c"]);
+        let req = query("/search");
+        assert!(Query::from_request_parts(&req).await.unwrap().0.is_empty());
+
+        for target in ["/?q=%", "/?q=%+1", "/?q=%zz", "/?q=%FF"] {
+            let rejection = Query::from_request_parts(&query(target)).await.err();
+            assert_eq!(rejection.unwrap().status, Status::BadRequest, "{target}");
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+
+use crate::{
+    extract::{FromRequest, Rejection},
+    guard::essence,
+    request::Request,
+    response::Status,
+};
+
+/// Decoded `application/x-www-form-urlencoded` pairs
+///
+/// Keeps the order of pairs and every value of repeated keys, like
+/// `tag=a&tag=b` from a multiple select.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormMap {
+    pairs: Vec<(String, String)>,
+}
+
+impl FormMap {
+    /// Decode `a=1&b=x+y`, `+` is space
+    ///
+    /// Fails on broken percent-encoding or bytes that are not UTF-8 after
+    /// decoding.
+    pub fn parse(input: &str) -> Result<Self> {
+        let pairs = input
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+                Ok((decode(k)?, decode(v)?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { pairs })
+    }
+
+    /// The first value of `key`
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of `key` in order
+    #[inline]
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    /// Count of pairs, include repeated keys
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Every pair in order
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Form body, reject with 415 for other content types and 400 for broken
+/// encoding
+impl FromRequest for FormMap {
+    type Rejection = Rejection;
+
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            if !req.headers.get("content-type").is_some_and(is_form) {
+                return Err(Rejection::new(
+                    Status::UnsupportedMediaType,
+                    "expected content type application/x-www-form-urlencoded",
+                ));
+            }
+            let body = Bytes::from_request(req).await?;
+            let body = std::str::from_utf8(&body)
+                .map_err(|e| Rejection::bad_request(format!("invalid form: {e}")))?;
+            Self::parse(body).map_err(|e| Rejection::bad_request(format!("invalid form: {e}")))
+        })
+    }
+}
+
+/// `+` is space, `%XX` must be two hex digits
+fn decode(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                // `from_str_radix` alone takes a sign, like `%+1`
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| anyhow!("invalid percent-encoding in {input:?}"))?;
+                decoded.push(hex);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| anyhow!("{input:?} is not UTF-8 after decoding"))
+}
+
+#[inline]
+fn is_form(content_type: &str) -> bool {
+    essence(content_type).eq_ignore_ascii_case("application/x-www-form-urlencoded")
+}
+
+#[cfg(feature = "form")]
+pub use typed::Form;
+
+#[cfg(feature = "form")]
+mod typed {
+    use futures::future::BoxFuture;
+    use serde::{
+        de::{
+            self,
+            value::{MapDeserializer, SeqDeserializer},
+            DeserializeOwned, IntoDeserializer, Visitor,
+        },
+        forward_to_deserialize_any,
+    };
+
+    use super::FormMap;
+    use crate::{
+        extract::{FromRequest, Rejection},
+        request::Request,
+        response::Status,
+    };
+
+    /// Form body deserialized to `T`
+    ///
+    /// Repeated keys fill `Vec` fields, other fields take the first value.
+    /// Broken encoding is rejected with 400 and form that doesn't fit `T`
+    /// with 422.
+    ///
+    /// ```not_rust
+    /// #[derive(Deserialize)]
+    /// struct Signup { name: String, age: u8, tags: Vec<String> }
+    ///
+    /// async fn signup(Form(signup): Form<Signup>) -> String {
+    ///     format!("welcome {}", signup.name)
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Form<T>(pub T);
+
+    impl<T> Form<T>
+    where
+        T: DeserializeOwned,
+    {
+        /// Deserialize decoded pairs to `T`
+        pub fn from_map(map: &FormMap) -> Result<T, de::value::Error> {
+            // group values of the same key, keep the order of first seen
+            let mut fields: Vec<(&str, Vec<&str>)> = vec![];
+            for (k, v) in map.iter() {
+                match fields.iter_mut().find(|(key, _)| *key == k) {
+                    Some((_, values)) => values.push(v),
+                    None => fields.push((k, vec![v])),
+                }
+            }
+            T::deserialize(MapDeserializer::new(
+                fields.into_iter().map(|(k, v)| (k, Values(v))),
+            ))
+        }
+    }
+
+    impl<T> FromRequest for Form<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        type Rejection = Rejection;
+
+        fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+            Box::pin(async move {
+                let map = FormMap::from_request(req).await?;
+                Self::from_map(&map).map(Form).map_err(|e| {
+                    Rejection::new(Status::UnprocessableContent, format!("invalid form: {e}"))
+                })
+            })
+        }
+    }
+
+    /// Every value of a key
+    struct Values<'de>(Vec<&'de str>);
+
+    impl<'de> IntoDeserializer<'de, de::value::Error> for Values<'de> {
+        type Deserializer = Self;
+
+        #[inline]
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    impl<'de> Values<'de> {
+        #[inline]
+        fn first(&self) -> &'de str {
+            self.0.first().copied().unwrap_or("")
+        }
+    }
+
+    /// Parse the first value with `FromStr`
+    macro_rules! deserialize_parse {
+        ($($method:ident => $visit:ident),+) => {
+            $(
+                fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                    let value = self.first();
+                    let parsed = value.parse().map_err(|e| {
+                        de::Error::custom(format!("invalid value {value:?}: {e}"))
+                    })?;
+                    visitor.$visit(parsed)
+                }
+            )+
+        };
+    }
+
+    impl<'de> de::Deserializer<'de> for Values<'de> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.len() {
+                1 => visitor.visit_borrowed_str(self.first()),
+                _ => self.deserialize_seq(visitor),
+            }
+        }
+
+        fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            visitor.visit_seq(SeqDeserializer::new(
+                self.0.into_iter().map(|v| Values(vec![v])),
+            ))
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            visitor.visit_some(self)
+        }
+
+        fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            visitor.visit_borrowed_str(self.first())
+        }
+
+        fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            self.deserialize_str(visitor)
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.first()
+                .into_deserializer()
+                .deserialize_enum(name, variants, visitor)
+        }
+
+        deserialize_parse!(
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_i128 => visit_i128,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_u128 => visit_u128,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char
+        );
+
+        forward_to_deserialize_any! {
+            bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier
+            ignored_any
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::HashMap;
+
+        use super::*;
+
+        #[test]
+        fn deserialize_repeated_keys() {
+            let map = FormMap::parse("tag=a&tag=b&name=x").unwrap();
+            let form = Form::<HashMap<String, Vec<String>>>::from_map(&map).unwrap();
+            assert_eq!(form["tag"], ["a", "b"]);
+            assert_eq!(form["name"], ["x"]);
+        }
+
+        #[test]
+        fn reject_invalid_values() {
+            let map = FormMap::parse("age=old").unwrap();
+            assert!(Form::<HashMap<String, u8>>::from_map(&map).is_err());
+            let map = FormMap::parse("age=42").unwrap();
+            assert_eq!(
+                Form::<HashMap<String, u8>>::from_map(&map).unwrap()["age"],
+                42
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_pairs() {
+        let form = FormMap::parse("name=J%C3%BCrgen+Doe&tag=a&tag=b&empty=&flag&&").unwrap();
+        assert_eq!(form.get("name"), Some("J\u{fc}rgen Doe"));
+        assert_eq!(form.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(form.get("empty"), Some(""));
+        assert_eq!(form.get("flag"), Some(""));
+        assert_eq!(form.len(), 5);
+        assert!(FormMap::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_plus_and_escapes() {
+        assert_eq!(decode("a+b").unwrap(), "a b");
+        assert_eq!(decode("a%2Bb").unwrap(), "a+b");
+        assert_eq!(decode("%3d%3D").unwrap(), "==");
+        assert_eq!(
+            FormMap::parse("k%3Dx=v%26y").unwrap().get("k=x"),
+            Some("v&y")
+        );
+    }
+
+    #[test]
+    fn reject_broken_escapes() {
+        for input in ["%", "%4", "a%", "%+1", "%-1", "%zz", "%g0", "% 1"] {
+            assert!(decode(input).is_err(), "{input:?}");
+            assert!(FormMap::parse(&format!("k={input}")).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn reject_invalid_utf8() {
+        assert!(decode("%FF").is_err());
+        assert!(decode("%C3").is_err());
+    }
+}
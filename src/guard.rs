@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use crate::{request::Request, response::Status};
+
+/// Predicate that a request must match before route's handler is called
+///
+/// When every candidate route of the same method and path rejected the
+/// request, the first rejected guard's status is responded.
+#[derive(Clone)]
+pub struct Guard {
+    /// Status to respond when no candidate matched
+    pub reject: Status,
+    predicate: Arc<dyn Fn(&Request) -> bool + Send + Sync>,
+}
+
+impl Guard {
+    /// Build guard from a custom predicate
+    ///
+    /// ## Arguments
+    ///
+    /// - `reject`: status to respond when no candidate matched
+    /// - `predicate`: return `true` when the request matches
+    #[inline]
+    pub fn new<P>(reject: Status, predicate: P) -> Self
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            reject,
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    #[inline]
+    pub fn check(&self, req: &Request) -> bool {
+        (self.predicate)(req)
+    }
+}
+
+/// Match a custom predicate, reject with 404
+#[inline]
+pub fn custom<P>(predicate: P) -> Guard
+where
+    P: Fn(&Request) -> bool + Send + Sync + 'static,
+{
+    Guard::new(Status::NotFound, predicate)
+}
+
+/// Match header's value case-insensitively, reject with 404
+#[inline]
+pub fn header(name: &str, value: &str) -> Guard {
+    let name = name.to_lowercase();
+    let value = value.to_owned();
+    custom(move |req| {
+        req.headers
+            .get(&name)
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case(&value))
+    })
+}
+
+/// Match the media type of `content-type` header, parameters like `charset`
+/// are ignored, reject with 415
+#[inline]
+pub fn content_type(mime: &str) -> Guard {
+    let mime = mime.to_owned();
+    Guard::new(Status::UnsupportedMediaType, move |req| {
+        req.headers
+            .get("content-type")
+            .is_some_and(|v| essence(v).eq_ignore_ascii_case(&mime))
+    })
+}
+
+/// Match `accept` header with `mime`, include `type/*` and `*/*`,
+/// reject with 406
+///
+/// The most specific matching range decides, `q=0` means not acceptable,
+/// like `application/json` for `*/*, application/json;q=0`. Request without
+/// `accept` header accepts any media type.
+#[inline]
+pub fn accept(mime: &str) -> Guard {
+    let mime = mime.to_lowercase();
+    Guard::new(Status::NotAcceptable, move |req| {
+        if !req.headers.contains("accept") {
+            return true;
+        }
+        let main = mime.split_once('/').map_or(mime.as_str(), |(m, _)| m);
+        req.headers
+            .get_all("accept")
+            .flat_map(|accept| accept.split(','))
+            .filter_map(|range| {
+                let (range, params) = range.split_once(';').unwrap_or((range, ""));
+                let range = range.trim().to_lowercase();
+                let specificity = if range == mime {
+                    2
+                } else if range.strip_suffix("/*").is_some_and(|r| r == main) {
+                    1
+                } else if range == "*/*" {
+                    0
+                } else {
+                    return None;
+                };
+                Some((specificity, quality(params)))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .is_some_and(|(_, q)| q > 0.0)
+    })
+}
+
+/// Weight of `q` parameter, `1` when it's missing or invalid
+#[inline]
+fn quality(params: &str) -> f32 {
+    params
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("q"))
+        .and_then(|(_, q)| q.trim().parse::<f32>().ok())
+        .filter(|q| (0.0..=1.0).contains(q))
+        .unwrap_or(1.0)
+}
+
+/// `text/html; charset=utf-8` is `text/html`
+#[inline]
+pub(crate) fn essence(value: &str) -> &str {
+    value.split(';').next().unwrap_or("").trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(accept: &str) -> Request {
+        let mut req = Request::default();
+        req.headers.insert("accept", accept).unwrap();
+        req
+    }
+
+    #[test]
+    fn accept_without_header() {
+        assert!(accept("application/json").check(&Request::default()));
+    }
+
+    #[test]
+    fn accept_ranges() {
+        let guard = accept("application/json");
+        assert!(guard.check(&request("application/json")));
+        assert!(guard.check(&request("Application/JSON; charset=utf-8")));
+        assert!(guard.check(&request("text/html, application/*")));
+        assert!(guard.check(&request("*/*")));
+        assert!(!guard.check(&request("text/html, text/*")));
+    }
+
+    #[test]
+    fn accept_quality() {
+        let guard = accept("application/json");
+        assert!(!guard.check(&request("application/json;q=0")));
+        assert!(!guard.check(&request("application/json; q=0.000, text/html")));
+        assert!(!guard.check(&request("*/*, application/json;q=0")));
+        assert!(!guard.check(&request("application/*;q=0, */*")));
+        assert!(guard.check(&request("application/*;q=0, application/json")));
+        assert!(guard.check(&request("application/json;q=0.1")));
+        // invalid weight is ignored
+        assert!(guard.check(&request("application/json;q=x")));
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Result};
+use arc_swap::ArcSwap;
+
+use crate::{
+    host::VirtualHosts,
+    http::method::Method,
+    router::{Route, Router},
+};
+
+/// Change routes while the app is serving
+///
+/// Every change is applied to a copy of current routes, then the copy is
+/// swapped in atomically. Requests always see an immutable snapshot, so
+/// serving requests never waits for a lock.
+///
+/// Route methods change the default host, or the host selected with
+/// [`RouterHandle::host`].
+///
+/// ```not_rust
+/// handle.add(Route::new(Method::Get, "/new", handler))?;
+/// handle.host("api.example.com").remove(&Method::Get, "/old")?;
+/// ```
+#[derive(Clone)]
+pub struct RouterHandle {
+    hosts: Arc<ArcSwap<VirtualHosts>>,
+    /// Serialize writers, so no change is lost
+    writer: Arc<Mutex<()>>,
+    /// Host pattern that route methods change, the default host when `None`
+    host: Option<String>,
+}
+
+impl Default for RouterHandle {
+    fn default() -> Self {
+        Self {
+            hosts: Arc::new(ArcSwap::from_pointee(VirtualHosts::default())),
+            writer: Arc::new(Mutex::new(())),
+            host: None,
+        }
+    }
+}
+
+impl RouterHandle {
+    /// Current routes of every host
+    #[inline]
+    pub fn snapshot(&self) -> Arc<VirtualHosts> {
+        self.hosts.load_full()
+    }
+
+    /// Handle that changes routes of host `pattern`, see [`VirtualHosts::add`]
+    ///
+    /// Route methods of the returned handle fail when no router is
+    /// registered for `pattern`.
+    #[inline]
+    pub fn host(&self, pattern: &str) -> Self {
+        Self {
+            host: Some(pattern.to_owned()),
+            ..self.clone()
+        }
+    }
+
+    /// Apply all changes in `f` at once, nothing changed when `f` failed
+    pub fn update<T>(&self, f: impl FnOnce(&mut VirtualHosts) -> Result<T>) -> Result<T> {
+        let _writer = self
+            .writer
+            .lock()
+            .map_err(|e| anyhow!("router handle poisoned {e}"))?;
+        let mut hosts = VirtualHosts::clone(&self.hosts.load());
+        let res = f(&mut hosts)?;
+        self.hosts.store(Arc::new(hosts));
+        Ok(res)
+    }
+
+    /// Add a route, fail when the same method and path already has an
+    /// unguarded route or the route's name is already registered
+    #[inline]
+    pub fn add(&self, route: Route) -> Result<()> {
+        self.update(|hosts| {
+            self.router(hosts)?
+                .merge_router(Router::new().register(route)?)
+        })
+    }
+
+    /// Add a route, replace all routes of the same method and path
+    ///
+    /// Fail when the route's name is registered by another route.
+    #[inline]
+    pub fn replace(&self, route: Route) -> Result<()> {
+        self.update(|hosts| self.router(hosts)?.replace_route(route))
+    }
+
+    /// Remove all routes of the method and path, fail when nothing removed
+    #[inline]
+    pub fn remove(&self, method: &Method, path: &str) -> Result<()> {
+        self.update(|hosts| {
+            if !self.router(hosts)?.remove_route(method, path) {
+                bail!("route {method} {path} not found");
+            }
+            Ok(())
+        })
+    }
+
+    /// Add static assets, fail when `route_path` already has assets
+    #[inline]
+    pub fn add_assets(&self, route_path: &str, assets_path: &Path) -> Result<()> {
+        self.update(|hosts| {
+            self.router(hosts)?
+                .merge_router(Router::new().assets(route_path, assets_path))
+        })
+    }
+
+    /// Remove static assets, fail when nothing removed
+    #[inline]
+    pub fn remove_assets(&self, route_path: &str) -> Result<()> {
+        self.update(|hosts| {
+            if self
+                .router(hosts)?
+                .assets_routes
+                .remove(route_path)
+                .is_none()
+            {
+                bail!("assets {route_path} not found");
+            }
+            Ok(())
+        })
+    }
+
+    /// Router of the selected host
+    #[inline]
+    fn router<'a>(&self, hosts: &'a mut VirtualHosts) -> Result<&'a mut Router> {
+        match &self.host {
+            Some(pattern) => hosts
+                .get_mut(pattern)
+                .ok_or_else(|| anyhow!("host {pattern} not found")),
+            None => Ok(&mut hosts.default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str) -> Route {
+        Route::new(Method::Get, path, || async { "ok" })
+    }
+
+    #[test]
+    fn change_routes_of_selected_host() {
+        let handle = RouterHandle::default();
+        handle
+            .update(|hosts| hosts.add("*.example.com", Router::new()))
+            .unwrap();
+        handle.host("*.Example.com").add(route("/api")).unwrap();
+        handle.add(route("/home")).unwrap();
+
+        let hosts = handle.snapshot();
+        assert!(hosts.select(Some("a.example.com")).find("/api").is_some());
+        assert!(hosts.select(Some("a.example.com")).find("/home").is_none());
+        assert!(hosts.select(None).find("/home").is_some());
+        assert!(hosts.select(None).find("/api").is_none());
+
+        handle
+            .host("*.example.com")
+            .remove(&Method::Get, "/api")
+            .unwrap();
+        assert!(handle
+            .snapshot()
+            .select(Some("a.example.com"))
+            .find("/api")
+            .is_none());
+    }
+
+    #[test]
+    fn fail_for_unknown_host() {
+        let handle = RouterHandle::default();
+        assert!(handle.host("example.com").add(route("/")).is_err());
+        assert!(handle.host("example.com").remove_assets("/").is_err());
+    }
+}
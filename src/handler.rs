@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use futures::{future::BoxFuture, Future};
+
+use crate::{
+    extract::{FromRequest, FromRequestParts},
+    http::header::HeaderMap,
+    request::Request,
+    response::{IntoResponse, Response, Status},
+};
+
+/// Async function that handles requests
+///
+/// Implemented for functions of these forms, `T` tells them apart:
+///
+/// - `async fn(Request, Response) -> anyhow::Result<Response>`, modify the
+///   passed response, fallback handlers receive it with status already set
+/// - `async fn(T1, T2, ..) -> impl IntoResponse` with up to 8 extractors,
+///   the last one may consume the request, see [`FromRequest`]
+///
+/// Handlers may also return `anyhow::Result<impl IntoResponse>`, an `Err`
+/// goes to the 500 fallback handler. When an extractor rejected the
+/// request, it's rejection is responded and the handler is not called.
+///
+/// Handlers that don't take the passed response still keep it's status
+/// unless they return another one, and it's headers they didn't set. So
+/// `async fn not_found() -> &'static str` answers 404, and 405 keeps it's
+/// `Allow` header.
+pub trait Handler<T>: Send + Sync + 'static {
+    fn call(
+        self: Arc<Self>,
+        req: Request,
+        res: Response,
+    ) -> BoxFuture<'static, anyhow::Result<Response>>;
+
+    /// The handler reads the body from it's stream, see
+    /// [`FromRequest::streams_body`]
+    #[inline]
+    fn streams_body() -> bool {
+        false
+    }
+}
+
+/// Return value of [`Handler`]
+pub trait HandlerOutput {
+    fn into_result(self) -> anyhow::Result<Response>;
+}
+
+impl<R: IntoResponse> HandlerOutput for R {
+    #[inline]
+    fn into_result(self) -> anyhow::Result<Response> {
+        Ok(self.into_response())
+    }
+}
+
+impl<R: IntoResponse> HandlerOutput for anyhow::Result<R> {
+    #[inline]
+    fn into_result(self) -> anyhow::Result<Response> {
+        self.map(IntoResponse::into_response)
+    }
+}
+
+/// Marker of handlers that take the passed response
+pub enum WithResponse {}
+
+impl<F, Fut> Handler<WithResponse> for F
+where
+    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: HandlerOutput,
+{
+    #[inline]
+    fn call(
+        self: Arc<Self>,
+        req: Request,
+        res: Response,
+    ) -> BoxFuture<'static, anyhow::Result<Response>> {
+        let fut = self(req, res);
+        Box::pin(async move { fut.await.into_result() })
+    }
+}
+
+impl<F, Fut> Handler<()> for F
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: HandlerOutput,
+{
+    #[inline]
+    fn call(
+        self: Arc<Self>,
+        _req: Request,
+        res: Response,
+    ) -> BoxFuture<'static, anyhow::Result<Response>> {
+        let fut = self();
+        Box::pin(async move { keep_passed(res, fut.await.into_result()?) })
+    }
+}
+
+/// Fill the response of a handler with the status and headers of the
+/// passed one, see [`Handler`]
+fn keep_passed(passed: Response, mut res: Response) -> anyhow::Result<Response> {
+    if res.status == Status::Ok {
+        res.status = passed.status;
+    }
+    let mut headers = HeaderMap::new();
+    for (k, v) in passed
+        .headers
+        .iter()
+        .filter(|(k, _)| !res.headers.contains(k))
+    {
+        headers.append(k, v)?;
+    }
+    for (k, v) in &res.headers {
+        headers.append(k, v)?;
+    }
+    res.headers = headers;
+    let mut extensions = passed.extensions;
+    extensions.extend(std::mem::take(&mut res.extensions));
+    res.extensions = extensions;
+    Ok(res)
+}
+
+macro_rules! extractor_handler {
+    ($($ty:ident),*; $last:ident) => {
+        impl<F, Fut, M, $($ty,)* $last> Handler<(M, $($ty,)* $last,)> for F
+        where
+            F: Fn($($ty,)* $last) -> Fut + Send + Sync + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: HandlerOutput,
+            $($ty: FromRequestParts + Send,)*
+            $last: FromRequest<M> + Send,
+            M: 'static,
+        {
+            #[allow(non_snake_case)]
+            fn call(
+                self: Arc<Self>,
+                req: Request,
+                res: Response,
+            ) -> BoxFuture<'static, anyhow::Result<Response>> {
+                Box::pin(async move {
+                    $(
+                        let $ty = match $ty::from_request_parts(&req).await {
+                            Ok(value) => value,
+                            Err(rejection) => return keep_passed(res, rejection.into_response()),
+                        };
+                    )*
+                    let $last = match $last::from_request(req).await {
+                        Ok(value) => value,
+                        Err(rejection) => return keep_passed(res, rejection.into_response()),
+                    };
+                    keep_passed(res, self($($ty,)* $last).await.into_result()?)
+                })
+            }
+
+            #[inline]
+            fn streams_body() -> bool {
+                $last::streams_body()
+            }
+        }
+    };
+}
+extractor_handler!(; T1);
+extractor_handler!(T1; T2);
+extractor_handler!(T1, T2; T3);
+extractor_handler!(T1, T2, T3; T4);
+extractor_handler!(T1, T2, T3, T4; T5);
+extractor_handler!(T1, T2, T3, T4, T5; T6);
+extractor_handler!(T1, T2, T3, T4, T5, T6; T7);
+extractor_handler!(T1, T2, T3, T4, T5, T6, T7; T8);
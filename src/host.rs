@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use log::error;
+
+use crate::{
+    request::Request,
+    response::{Response, Status},
+    router::{BoxHandler, Router},
+};
+
+/// Routers selected by request host before path routing
+#[derive(Default, Clone)]
+pub struct VirtualHosts {
+    /// Exact host names like `example.com`
+    pub exact: HashMap<String, Router>,
+    /// Wildcard subdomains like `*.internal`, stored as `.internal`
+    ///
+    /// Sorted by suffix length, the most specific one first.
+    pub wildcard: Vec<(String, Router)>,
+    /// Serve requests that no host matched, the app's own routes
+    pub default: Router,
+}
+
+impl VirtualHosts {
+    /// Registry router for host `pattern`, merge into the router that
+    /// already registered for the same pattern
+    ///
+    /// ## Arguments
+    ///
+    /// - `pattern`: `example.com` or `*.example.com`
+    /// - `router`: routes for the host
+    pub fn add(&mut self, pattern: &str, router: Router) -> Result<()> {
+        let pattern = normalize_host(pattern);
+        if let Some(suffix) = pattern.strip_prefix('*') {
+            if !suffix.starts_with('.') || suffix.len() < 2 || suffix.contains('*') {
+                bail!("invalid wildcard host {pattern}");
+            }
+            match self.wildcard.iter_mut().find(|(s, _)| s == suffix) {
+                Some((_, exists)) => exists.merge_router(router)?,
+                None => {
+                    self.wildcard.push((suffix.to_owned(), router));
+                    self.wildcard
+                        .sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+                }
+            }
+        } else {
+            if pattern.is_empty() || pattern.contains('*') {
+                bail!("invalid host {pattern}");
+            }
+            match self.exact.get_mut(&pattern) {
+                Some(exists) => exists.merge_router(router)?,
+                None => {
+                    self.exact.insert(pattern, router);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Router registered for host `pattern`, `example.com` or `*.example.com`
+    #[inline]
+    pub fn get_mut(&mut self, pattern: &str) -> Option<&mut Router> {
+        let pattern = normalize_host(pattern);
+        match pattern.strip_prefix('*') {
+            Some(suffix) => self
+                .wildcard
+                .iter_mut()
+                .find(|(s, _)| s == suffix)
+                .map(|(_, router)| router),
+            None => self.exact.get_mut(&pattern),
+        }
+    }
+
+    /// Select router by request host, use the default one when no host matched
+    ///
+    /// The host comes from absolute-form target or `host` header,
+    /// see [`Request::host`].
+    #[inline]
+    pub fn select(&self, host: Option<&str>) -> &Router {
+        let Some(host) = host.map(normalize_host) else {
+            return &self.default;
+        };
+        self.exact
+            .get(&host)
+            .or_else(|| {
+                self.wildcard
+                    .iter()
+                    .find(|(suffix, _)| {
+                        host.ends_with(suffix.as_str()) && host.len() > suffix.len()
+                    })
+                    .map(|(_, router)| router)
+            })
+            .unwrap_or(&self.default)
+    }
+
+    /// Find fallback handler in selected router, then the app's
+    #[inline]
+    pub fn fallback_handler<'a>(
+        &'a self,
+        router: &'a Router,
+        path: &str,
+        status: &Status,
+    ) -> Option<&'a BoxHandler> {
+        router.fallback_handler(path, status).or_else(|| {
+            if std::ptr::eq(router, &self.default) {
+                None
+            } else {
+                self.default.fallbacks.get(status)
+            }
+        })
+    }
+
+    /// Call fallback handler for `res.status`
+    ///
+    /// Return `res` as is when no handler registered or the handler failed.
+    pub(crate) async fn fallback(&self, router: &Router, req: Request, res: Response) -> Response {
+        let Some(handler) = self.fallback_handler(router, req.uri.path(), &res.status) else {
+            return res;
+        };
+        let status = res.status.clone();
+        match handler(req, res).await {
+            Ok(res) => res,
+            Err(err) => {
+                error!("fallback handler failed {err}");
+                Response {
+                    status,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+/// Lowercase host and remove port and trailing dot
+///
+/// `Example.COM.:8080` is `example.com`, `[::1]:80` is `[::1]`
+#[inline]
+fn normalize_host(host: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if !port.contains(']') => h,
+        _ => host,
+    };
+    host.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Router with a single route `/{tag}`
+    fn tagged(tag: &str) -> Router {
+        Router::new().get(&format!("/{tag}"), || async { "" })
+    }
+
+    fn tag(router: &Router) -> &str {
+        router.routes.keys().next().map_or("default", |p| &p[1..])
+    }
+
+    fn hosts() -> VirtualHosts {
+        let mut hosts = VirtualHosts::default();
+        hosts.add("api.internal", tagged("exact")).unwrap();
+        hosts.add("*.internal", tagged("wildcard")).unwrap();
+        hosts.add("*.eu.internal", tagged("eu")).unwrap();
+        hosts.add("Example.COM.", tagged("example")).unwrap();
+        hosts.add("[::1]", tagged("ipv6")).unwrap();
+        hosts
+    }
+
+    #[test]
+    fn select_exact_before_wildcard() {
+        let hosts = hosts();
+        for (host, expected) in [
+            (Some("api.internal"), "exact"),
+            (Some("web.internal"), "wildcard"),
+            (Some("a.b.internal"), "wildcard"),
+            (Some("web.eu.internal"), "eu"),
+            (Some("eu.internal"), "wildcard"),
+            (Some("internal"), "default"),
+            (Some("xinternal"), "default"),
+            (Some("example.com"), "example"),
+            (Some("other.com"), "default"),
+            (None, "default"),
+        ] {
+            assert_eq!(tag(hosts.select(host)), expected, "{host:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_host_header() {
+        assert_eq!(normalize_host("Example.COM.:8080"), "example.com");
+        assert_eq!(normalize_host("example.com"), "example.com");
+        assert_eq!(normalize_host("[::1]:80"), "[::1]");
+        assert_eq!(normalize_host("[::1]"), "[::1]");
+
+        let hosts = hosts();
+        for (host, expected) in [
+            ("API.internal:8080", "exact"),
+            ("web.internal.", "wildcard"),
+            ("EXAMPLE.com:443", "example"),
+            ("[::1]:80", "ipv6"),
+        ] {
+            assert_eq!(tag(hosts.select(Some(host))), expected, "{host}");
+        }
+    }
+
+    #[test]
+    fn absolute_form_overrides_host_header() {
+        let hosts = hosts();
+        let select = |head: &str| {
+            let req = Request::parse_from_bytes(head.to_owned().into()).unwrap();
+            tag(hosts.select(req.host())).to_owned()
+        };
+        assert_eq!(
+            select("GET http://api.internal/ HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            "exact"
+        );
+        assert_eq!(
+            select("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            "example"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_patterns() {
+        let mut hosts = VirtualHosts::default();
+        for pattern in [
+            "",
+            "*",
+            "*.",
+            "*internal",
+            "**.internal",
+            "*.*.internal",
+            "a.*.b",
+        ] {
+            assert!(hosts.add(pattern, Router::new()).is_err(), "{pattern:?}");
+        }
+        assert!(hosts.exact.is_empty() && hosts.wildcard.is_empty());
+    }
+}
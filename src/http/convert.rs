@@ -0,0 +1,378 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use http::{header::HeaderName, HeaderValue, StatusCode, Version};
+
+use super::{
+    extensions::Extensions,
+    header::HeaderMap,
+    method::Method,
+    request::{Request, DEFAULT_BODY_LIMIT},
+    response::{BodyStream, Response, Status},
+    uri::Uri,
+};
+use crate::{
+    router::{BoxState, Params},
+    url::UrlBuilder,
+};
+
+/// Routing state of a request that `http` types have no place for, kept in
+/// extensions so the conversion back restores it
+#[derive(Clone, Default)]
+struct RouteParts {
+    nest_path: String,
+    params: Params,
+    urls: UrlBuilder,
+    state: Option<BoxState>,
+    peer_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    body_limit: Option<usize>,
+    /// Body stream of streaming routes
+    incoming: Arc<Mutex<Option<BodyStream>>>,
+}
+
+impl From<http::Method> for Method {
+    #[inline]
+    fn from(method: http::Method) -> Self {
+        Self::from(&method)
+    }
+}
+
+impl From<&http::Method> for Method {
+    #[inline]
+    fn from(method: &http::Method) -> Self {
+        match *method {
+            http::Method::GET => Self::Get,
+            http::Method::HEAD => Self::Head,
+            http::Method::POST => Self::Post,
+            http::Method::PUT => Self::Put,
+            http::Method::DELETE => Self::Delete,
+            http::Method::CONNECT => Self::Connect,
+            http::Method::OPTIONS => Self::Options,
+            http::Method::TRACE => Self::Trace,
+            http::Method::PATCH => Self::Patch,
+            // `http` validated the token already
+            _ => Self::Extension(method.as_str().to_owned()),
+        }
+    }
+}
+
+impl TryFrom<&Method> for http::Method {
+    type Error = anyhow::Error;
+
+    /// Fails only for an `Extension` built by hand with invalid token
+    #[inline]
+    fn try_from(method: &Method) -> Result<Self> {
+        Ok(Self::from_bytes(method.as_str().as_bytes())?)
+    }
+}
+
+impl TryFrom<Method> for http::Method {
+    type Error = anyhow::Error;
+
+    #[inline]
+    fn try_from(method: Method) -> Result<Self> {
+        Self::try_from(&method)
+    }
+}
+
+impl From<&Status> for StatusCode {
+    #[inline]
+    fn from(status: &Status) -> Self {
+        // codes out of range are only possible with a hand built `Custom`
+        Self::from_u16(status.code()).unwrap_or(Self::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl From<Status> for StatusCode {
+    #[inline]
+    fn from(status: Status) -> Self {
+        Self::from(&status)
+    }
+}
+
+impl From<StatusCode> for Status {
+    /// Unregistered codes take the reason `http` knows, if any
+    #[inline]
+    fn from(status: StatusCode) -> Self {
+        let code = status.as_u16();
+        match Self::from_code(code) {
+            Ok(Self::Custom(..)) | Err(_) => {
+                Self::Custom(code, status.canonical_reason().unwrap_or("").to_owned())
+            }
+            Ok(status) => status,
+        }
+    }
+}
+
+impl TryFrom<&HeaderMap> for http::HeaderMap {
+    type Error = anyhow::Error;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self> {
+        let mut map = Self::with_capacity(headers.len());
+        for (k, v) in headers {
+            map.append(
+                HeaderName::from_bytes(k.as_bytes())?,
+                HeaderValue::from_str(v)?,
+            );
+        }
+        Ok(map)
+    }
+}
+
+impl TryFrom<&http::HeaderMap> for HeaderMap {
+    type Error = anyhow::Error;
+
+    /// Names are lowercase, fail on value that is not UTF-8
+    fn try_from(headers: &http::HeaderMap) -> Result<Self> {
+        let mut map = Self::new();
+        for (k, v) in headers {
+            map.append(k.as_str(), std::str::from_utf8(v.as_bytes())?)?;
+        }
+        Ok(map)
+    }
+}
+
+impl<B> TryFrom<Request> for http::Request<B>
+where
+    B: From<Bytes>,
+{
+    type Error = anyhow::Error;
+
+    /// Routing state and extensions are kept for the conversion back
+    ///
+    /// Lossy parts: `http` lowercases header names, and the fragment of
+    /// the target is already dropped when it's parsed.
+    fn try_from(req: Request) -> Result<Self> {
+        let mut http_req = http::Request::builder()
+            .method(http::Method::try_from(&req.method)?)
+            .uri(req.uri.to_string())
+            .version(to_version(&req.version)?)
+            .body(B::from(req.body))?;
+        *http_req.headers_mut() = http::HeaderMap::try_from(&req.headers)?;
+        http_req.extensions_mut().insert(RouteParts {
+            nest_path: req.nest_path,
+            params: req.params,
+            urls: req.urls,
+            state: req.state,
+            peer_addr: req.peer_addr,
+            local_addr: req.local_addr,
+            body_limit: Some(req.body_limit),
+            incoming: req.incoming,
+        });
+        http_req.extensions_mut().insert(req.extensions);
+        Ok(http_req)
+    }
+}
+
+impl<B> TryFrom<http::Request<B>> for Request
+where
+    B: Into<Bytes>,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(req: http::Request<B>) -> Result<Self> {
+        let (mut parts, body) = req.into_parts();
+        let route = parts.extensions.remove::<RouteParts>().unwrap_or_default();
+        let extensions = parts.extensions.remove::<Extensions>();
+        Ok(Self {
+            uri: Uri::parse(&parts.uri.to_string())?,
+            nest_path: route.nest_path,
+            params: route.params,
+            urls: route.urls,
+            method: Method::from(parts.method),
+            version: from_version(parts.version)?.to_owned(),
+            headers: HeaderMap::try_from(&parts.headers)?,
+            body: body.into(),
+            extensions: extensions.unwrap_or_default(),
+            state: route.state,
+            peer_addr: route.peer_addr,
+            local_addr: route.local_addr,
+            body_limit: route.body_limit.unwrap_or(DEFAULT_BODY_LIMIT),
+            incoming: route.incoming,
+        })
+    }
+}
+
+impl<B> TryFrom<Response> for http::Response<B>
+where
+    B: From<Bytes>,
+{
+    type Error = anyhow::Error;
+
+    /// Fails for streaming body, collect it first
+    ///
+    /// Lossy parts: `http` lowercases header names and has no reason phrase,
+    /// a [`Status::Custom`] reason comes back as the one `http` knows, if
+    /// any.
+    fn try_from(res: Response) -> Result<Self> {
+        if res.stream.is_some() {
+            bail!("streaming body can't be converted");
+        }
+        let mut http_res = http::Response::new(B::from(res.body));
+        *http_res.status_mut() = StatusCode::from(&res.status);
+        *http_res.headers_mut() = http::HeaderMap::try_from(&res.headers)?;
+        http_res.extensions_mut().insert(res.extensions);
+        Ok(http_res)
+    }
+}
+
+impl<B> TryFrom<http::Response<B>> for Response
+where
+    B: Into<Bytes>,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(res: http::Response<B>) -> Result<Self> {
+        let (mut parts, body) = res.into_parts();
+        Ok(Self {
+            headers: HeaderMap::try_from(&parts.headers)?,
+            body: body.into(),
+            status: Status::from(parts.status),
+            stream: None,
+            extensions: parts.extensions.remove().unwrap_or_default(),
+        })
+    }
+}
+
+/// Request built by hand has no version, send it as HTTP/1.1
+#[inline]
+fn to_version(version: &str) -> Result<Version> {
+    let version = match version {
+        "HTTP/0.9" => Version::HTTP_09,
+        "HTTP/1.0" => Version::HTTP_10,
+        "HTTP/1.1" | "" => Version::HTTP_11,
+        "HTTP/2" | "HTTP/2.0" => Version::HTTP_2,
+        "HTTP/3" | "HTTP/3.0" => Version::HTTP_3,
+        _ => bail!("unsupported version {version}"),
+    };
+    Ok(version)
+}
+
+#[inline]
+fn from_version(version: Version) -> Result<&'static str> {
+    let version = match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => bail!("unsupported version {version:?}"),
+    };
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt};
+
+    use super::*;
+
+    fn request(head: &str) -> Request {
+        let head = format!("{head}\r\nHost: x\r\nAccept: a\r\naccept: b\r\nX-Case: 1\r\n\r\n");
+        Request::parse_from_bytes(head.into()).unwrap()
+    }
+
+    #[test]
+    fn request_round_trip() {
+        for (head, method, target) in [
+            ("GET /where?q=now HTTP/1.1", Method::Get, "/where?q=now"),
+            (
+                "PURGE http://example.org/pub?x=1 HTTP/1.1",
+                Method::Extension("PURGE".to_owned()),
+                "http://example.org/pub?x=1",
+            ),
+            (
+                "CONNECT example.com:443 HTTP/1.1",
+                Method::Connect,
+                "example.com:443",
+            ),
+            ("OPTIONS * HTTP/1.1", Method::Options, "*"),
+        ] {
+            let mut req = request(head);
+            req.body = Bytes::from_static(b"body");
+            req.params = vec![("id".to_owned(), "7".to_owned())];
+            req.nest_path = "/api".to_owned();
+            req.extensions.insert(42u32);
+
+            let http_req = http::Request::<Bytes>::try_from(req.clone()).unwrap();
+            assert_eq!(http_req.method().as_str(), method.as_str(), "{head}");
+            assert_eq!(http_req.uri().to_string(), target, "{head}");
+            assert_eq!(http_req.headers().get_all("accept").iter().count(), 2);
+            assert_eq!(http_req.body(), "body");
+
+            let back = Request::try_from(http_req).unwrap();
+            assert_eq!(back.method, method, "{head}");
+            assert_eq!(back.uri, req.uri, "{head}");
+            assert_eq!(back.version, "HTTP/1.1");
+            assert_eq!(
+                back.headers.get_all("Accept").collect::<Vec<_>>(),
+                ["a", "b"]
+            );
+            assert_eq!(back.body, "body");
+            assert_eq!(back.params, req.params);
+            assert_eq!(back.nest_path, "/api");
+            assert_eq!(back.extensions.get::<u32>(), Some(&42));
+            // header names come back lowercase
+            assert!(back.headers.iter().any(|h| h == ("x-case", "1")));
+        }
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let mut res = Response {
+            status: Status::Created,
+            body: Bytes::from_static(b"created"),
+            ..Default::default()
+        };
+        res.headers.append("Set-Cookie", "a=1").unwrap();
+        res.headers.append("Set-Cookie", "b=2").unwrap();
+        res.extensions.insert("ext");
+
+        let http_res = http::Response::<Bytes>::try_from(res).unwrap();
+        assert_eq!(http_res.status(), StatusCode::CREATED);
+        assert_eq!(http_res.headers().get_all("set-cookie").iter().count(), 2);
+
+        let back = Response::try_from(http_res).unwrap();
+        assert_eq!(back.status, Status::Created);
+        assert_eq!(
+            back.headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+        assert_eq!(back.body, "created");
+        assert_eq!(back.extensions.get::<&str>(), Some(&"ext"));
+
+        assert!(
+            http::Response::<Bytes>::try_from(Response::stream(stream::empty().boxed())).is_err()
+        );
+    }
+
+    #[test]
+    fn status_round_trip() {
+        for code in 100..=999 {
+            let status = Status::from_code(code).unwrap();
+            let http_status = StatusCode::from(&status);
+            assert_eq!(http_status.as_u16(), code);
+            match status {
+                // custom reasons are lost
+                Status::Custom(..) => assert_eq!(
+                    Status::from(http_status),
+                    Status::Custom(
+                        code,
+                        http_status.canonical_reason().unwrap_or("").to_owned()
+                    )
+                ),
+                status => assert_eq!(Status::from(http_status), status),
+            }
+        }
+        let custom = Status::Custom(599, "Network Timeout".to_owned());
+        assert_eq!(
+            Status::from(StatusCode::from(&custom)),
+            Status::Custom(599, String::new())
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+};
+
+/// Values keyed by their type, one value of every type
+///
+/// Middleware passes data like authenticated user or request id to
+/// downstream handlers with it, see [`Request::extensions`] and
+/// [`Response::extensions`].
+///
+/// ```not_rust
+/// async fn auth(mut req: Request, res: Response, next: Next) -> Result<Response> {
+///     req.extensions.insert(User { id: 1 });
+///     next.run(req, res).await
+/// }
+///
+/// async fn me(Extension(user): Extension<User>) -> String {
+///     format!("user {}", user.id)
+/// }
+/// ```
+///
+/// [`Request::extensions`]: super::request::Request::extensions
+/// [`Response::extensions`]: super::response::Response::extensions
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn AnyClone>>,
+}
+
+impl Extensions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert `value`, return the replaced value of the same type
+    #[inline]
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|v| v.into_any().downcast().ok())
+            .map(|v| *v)
+    }
+
+    #[inline]
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|v| (**v).as_any().downcast_ref())
+    }
+
+    #[inline]
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|v| (**v).as_any_mut().downcast_mut())
+    }
+
+    #[inline]
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|v| v.into_any().downcast().ok())
+            .map(|v| *v)
+    }
+
+    #[inline]
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Move all values of `other` into this, replace values of the same type
+    #[inline]
+    pub fn extend(&mut self, other: Extensions) {
+        self.map.extend(other.map);
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+/// Cloneable `Any`, requests are cloned with their extensions
+///
+/// `Box<dyn AnyClone>` is `AnyClone` too, always call it's methods on the
+/// boxed value.
+trait AnyClone: Any + Send + Sync {
+    fn clone_box(&self) -> Box<dyn AnyClone>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T> AnyClone for T
+where
+    T: Clone + Send + Sync + 'static,
+{
+    #[inline]
+    fn clone_box(&self) -> Box<dyn AnyClone> {
+        Box::new(self.clone())
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    #[inline]
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Clone for Box<dyn AnyClone> {
+    #[inline]
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
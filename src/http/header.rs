@@ -0,0 +1,210 @@
+use std::slice;
+
+use anyhow::{bail, Result};
+
+use super::method::is_tchar;
+
+/// HTTP headers
+///
+/// Keeps the order that headers were added and every value of repeated
+/// headers, like multiple `Set-Cookie`. Names are looked up
+/// case-insensitively but sent as they were added.
+///
+/// Names must be tokens and values must not contain CR, LF or other control
+/// characters except tab, so headers can't be injected into a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count of header lines, include repeated ones
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The first value of header `name`
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of header `name` in order
+    #[inline]
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+
+    /// Set header `name` to `value`, replace all values it already has
+    ///
+    /// The header keeps the position of it's first value.
+    pub fn insert(&mut self, name: &str, value: &str) -> Result<()> {
+        check(name, value)?;
+        match self
+            .entries
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            Some(i) => {
+                self.entries[i] = (name.to_owned(), value.to_owned());
+                let rest = self.entries.split_off(i + 1);
+                self.entries.extend(
+                    rest.into_iter()
+                        .filter(|(k, _)| !k.eq_ignore_ascii_case(name)),
+                );
+            }
+            None => self.entries.push((name.to_owned(), value.to_owned())),
+        }
+        Ok(())
+    }
+
+    /// Add a value to header `name`, keep values it already has
+    #[inline]
+    pub fn append(&mut self, name: &str, value: &str) -> Result<()> {
+        check(name, value)?;
+        self.entries.push((name.to_owned(), value.to_owned()));
+        Ok(())
+    }
+
+    /// Remove every value of header `name`, return them in order
+    #[inline]
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let (removed, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|(k, _)| k.eq_ignore_ascii_case(name));
+        self.entries = kept;
+        removed.into_iter().map(|(_, v)| v).collect()
+    }
+
+    /// Every header line in order
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator of header lines, see [`HeaderMap::iter`]
+pub struct Iter<'a>(slice::Iter<'a, (String, String)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a str);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Validate name is a token and value has no control characters
+#[inline]
+fn check(name: &str, value: &str) -> Result<()> {
+    if name.is_empty() || !name.bytes().all(is_tchar) {
+        bail!("invalid header name {name:?}");
+    }
+    if value.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+        bail!("invalid value of header {name}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Response;
+
+    #[test]
+    fn reject_injection() {
+        let mut headers = HeaderMap::new();
+        for value in ["a\r\nX-Injected: 1", "a\nb", "a\rb", "a\0b", "a\x7fb"] {
+            assert!(headers.insert("X-Value", value).is_err(), "{value:?}");
+            assert!(headers.append("X-Value", value).is_err(), "{value:?}");
+        }
+        for name in [
+            "",
+            "X Value",
+            "X-Value:",
+            "X\r\nValue",
+            "X(Value)",
+            "X\u{e9}",
+        ] {
+            assert!(headers.insert(name, "v").is_err(), "{name:?}");
+            assert!(headers.append(name, "v").is_err(), "{name:?}");
+        }
+        assert!(headers.is_empty());
+        assert!(headers.insert("X-Value", "tab\tand \"quotes\"").is_ok());
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        let mut headers = HeaderMap::new();
+        headers.append("Content-Type", "text/plain").unwrap();
+        headers.append("accept", "a").unwrap();
+        headers.append("ACCEPT", "b").unwrap();
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert_eq!(headers.get_all("Accept").collect::<Vec<_>>(), ["a", "b"]);
+        assert!(headers.contains("CONTENT-TYPE"));
+        assert_eq!(headers.remove("Accept"), ["a", "b"]);
+        assert_eq!(headers.len(), 1);
+        // names are kept as added
+        assert_eq!(headers.iter().next(), Some(("Content-Type", "text/plain")));
+    }
+
+    #[test]
+    fn insert_replaces_in_place() {
+        let mut headers = HeaderMap::new();
+        headers.append("Vary", "a").unwrap();
+        headers.append("X-First", "1").unwrap();
+        headers.append("vary", "b").unwrap();
+        headers.append("X-Last", "2").unwrap();
+        headers.insert("VARY", "c").unwrap();
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("VARY", "c"), ("X-First", "1"), ("X-Last", "2")]
+        );
+    }
+
+    #[test]
+    fn serialize_every_set_cookie() {
+        let mut res = Response::default();
+        res.headers.append("Set-Cookie", "a=1; Path=/").unwrap();
+        res.headers.append("Set-Cookie", "b=2").unwrap();
+        let bytes: Vec<u8> = res.into();
+        let head = String::from_utf8(bytes).unwrap();
+        assert!(head.contains("\r\nSet-Cookie: a=1; Path=/\r\n"), "{head}");
+        assert!(head.contains("\r\nSet-Cookie: b=2\r\n"), "{head}");
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Result};
+
+/// HTTP request method
+///
+/// Methods are case-sensitive, `get` is an extension method rather than `GET`.
+///
+/// https://www.rfc-editor.org/rfc/rfc9110#section-9
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Method {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    /// Extension method like `PURGE`, `PROPFIND` or `REPORT`, build it with
+    /// [`Method::extension`] so standard names get their own variant
+    Extension(String),
+}
+
+impl Method {
+    /// Parse method token from request line or user input
+    #[inline]
+    pub fn parse(method: &str) -> Result<Self> {
+        use Method::*;
+
+        let method = match method {
+            "GET" => Get,
+            "HEAD" => Head,
+            "POST" => Post,
+            "PUT" => Put,
+            "DELETE" => Delete,
+            "CONNECT" => Connect,
+            "OPTIONS" => Options,
+            "TRACE" => Trace,
+            "PATCH" => Patch,
+            _ if method.is_empty() || !method.bytes().all(is_tchar) => {
+                bail!("invalid method {method}")
+            }
+            _ => Extension(method.to_owned()),
+        };
+        Ok(method)
+    }
+
+    /// Build an extension method, the name must be a valid token
+    ///
+    /// Standard names are normalized, `extension("GET")` is [`Method::Get`].
+    #[inline]
+    pub fn extension(method: &str) -> Result<Self> {
+        Self::parse(method)
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        use Method::*;
+
+        match self {
+            Get => "GET",
+            Head => "HEAD",
+            Post => "POST",
+            Put => "PUT",
+            Delete => "DELETE",
+            Connect => "CONNECT",
+            Options => "OPTIONS",
+            Trace => "TRACE",
+            Patch => "PATCH",
+            Extension(method) => method,
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Display for Method {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." /
+///         "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA
+///
+/// https://www.rfc-editor.org/rfc/rfc9110#section-5.6.2
+#[inline]
+pub(crate) fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+        || matches!(
+            b,
+            b'!' | b'#'
+                | b'$'
+                | b'%'
+                | b'&'
+                | b'\''
+                | b'*'
+                | b'+'
+                | b'-'
+                | b'.'
+                | b'^'
+                | b'_'
+                | b'`'
+                | b'|'
+                | b'~'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_standard_methods() {
+        for method in [
+            "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+        ] {
+            let parsed = Method::parse(method).unwrap();
+            assert!(!matches!(parsed, Method::Extension(_)), "{method}");
+            assert_eq!(parsed.as_str(), method);
+        }
+    }
+
+    #[test]
+    fn parse_extension_methods() {
+        assert_eq!(
+            Method::parse("PURGE").unwrap(),
+            Method::Extension("PURGE".to_owned())
+        );
+        assert_eq!(
+            Method::parse("M-SEARCH").unwrap(),
+            Method::Extension("M-SEARCH".to_owned())
+        );
+        // methods are case-sensitive
+        assert_eq!(
+            Method::parse("get").unwrap(),
+            Method::Extension("get".to_owned())
+        );
+    }
+
+    #[test]
+    fn extension_normalizes_standard_names() {
+        assert_eq!(Method::extension("GET").unwrap(), Method::Get);
+        assert_eq!(Method::extension("PATCH").unwrap(), Method::Patch);
+        assert_eq!(
+            Method::extension("PROPFIND").unwrap(),
+            Method::Extension("PROPFIND".to_owned())
+        );
+    }
+
+    #[test]
+    fn reject_invalid_tokens() {
+        for method in ["", "GE T", "GET\r", "P(OST)", "\u{e9}", "a/b", "{}"] {
+            assert!(Method::parse(method).is_err(), "{method:?}");
+            assert!(Method::extension(method).is_err(), "{method:?}");
+        }
+    }
+}
@@ -1,3 +1,10 @@
+#[cfg(feature = "http")]
+pub mod convert;
+pub mod extensions;
+pub mod header;
+pub mod method;
 pub mod mime;
 pub mod request;
 pub mod response;
+pub mod status;
+pub mod uri;
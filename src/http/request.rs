@@ -1,26 +1,79 @@
-use std::{collections::HashMap, io::ErrorKind, path::PathBuf};
+use std::{
+    io::ErrorKind,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
 
-use anyhow::{anyhow, bail, Result};
-use bytes::{BufMut, Bytes, BytesMut};
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{stream, StreamExt, TryStreamExt};
 use log::trace;
-use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
+use super::{
+    extensions::Extensions, header::HeaderMap, method::Method, response::BodyStream, uri::Uri,
+};
+use crate::{
+    error::Error,
+    router::{BoxState, Params},
+    url::UrlBuilder,
+};
+
+/// Size of a chunk size line or a trailer line of chunked body
+const MAX_CHUNK_LINE: usize = 4096;
+/// Bytes [`Request::read_body`] reads unless the app sets another limit,
+/// see [`crate::Rymo::body_limit`]
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+#[derive(Clone)]
 pub struct Request {
-    pub path: PathBuf,
-    pub method: String,
+    /// Request target, see [`Uri`] for all forms
+    pub uri: Uri,
+    /// Prefix of nested routers that matched this request
+    pub nest_path: String,
+    /// Path parameters that route pattern matched, percent decoded
+    pub params: Params,
+    /// URL builder of the router that serves this request
+    pub urls: UrlBuilder,
+    pub method: Method,
     pub version: String,
-    pub headers: HashMap<String, String>,
+    pub headers: HeaderMap,
+    /// Whole body, empty for routes that stream it, see
+    /// [`Request::take_body_stream`]
     pub body: Bytes,
+    /// Typed values that middleware pass to downstream handlers
+    pub extensions: Extensions,
+    /// Remote address of the connection, `None` when the request is not
+    /// read from a socket, like one converted from `http`
+    pub peer_addr: Option<SocketAddr>,
+    /// Local address that accepted the connection
+    pub local_addr: Option<SocketAddr>,
+    /// Bytes [`Request::read_body`] reads at most, set by the router that
+    /// serves this request
+    pub body_limit: usize,
+    /// Body not read from the connection yet, shared by clones
+    pub(crate) incoming: Arc<Mutex<Option<BodyStream>>>,
+    /// State of the route that serves this request, see [`Request::state`]
+    pub(crate) state: Option<BoxState>,
 }
 
 impl Default for Request {
     fn default() -> Self {
         Self {
-            path: PathBuf::new(),
-            method: "".to_owned(),
+            uri: Uri::default(),
+            nest_path: String::new(),
+            params: vec![],
+            urls: UrlBuilder::default(),
+            method: Method::default(),
             version: "".to_owned(),
-            headers: HashMap::new(),
+            headers: HeaderMap::new(),
             body: Bytes::new(),
+            extensions: Extensions::new(),
+            peer_addr: None,
+            local_addr: None,
+            body_limit: DEFAULT_BODY_LIMIT,
+            incoming: Arc::default(),
+            state: None,
         }
     }
 }
@@ -36,22 +89,18 @@ impl Request {
             // GET /v1/ HTTP/1.1
             if i == 0 {
                 let route = l.split(|&b| b == b' ');
-                let (method, path, version) = route.enumerate().try_fold(
-                    (String::new(), PathBuf::new(), String::new()),
+                let (method, uri, version) = route.enumerate().try_fold(
+                    (Method::default(), Uri::default(), String::new()),
                     fold_first_line,
                 )?;
                 req.method = method;
-                req.path = path;
+                req.uri = uri;
                 req.version = version;
                 anyhow::Ok(())
                 // the second line is headers until \r\n\r\n
             } else {
-                let heads = std::str::from_utf8(l)?.split(": ");
-                let (k, v) = heads
-                    .enumerate()
-                    .try_fold((String::new(), String::new()), fold_headers)?;
-                req.headers.entry(k).or_insert(v);
-                Ok(())
+                let (k, v) = parse_header(std::str::from_utf8(l)?)?;
+                req.headers.append(k, v)
             }
         };
 
@@ -63,8 +112,168 @@ impl Request {
             .enumerate()
             .try_for_each(collect_headers)?;
 
+        req.check_target()?;
         Ok(req)
     }
+
+    /// The path used for routing, see [`Uri::path`]
+    ///
+    /// Handlers of nested router see the path without it's prefix.
+    #[inline]
+    pub fn path(&self) -> &str {
+        let path = self.uri.path();
+        match path.strip_prefix(self.nest_path.as_str()) {
+            Some("") => "/",
+            Some(p) => p,
+            None => path,
+        }
+    }
+
+    /// Path parameter of `:param` or `*wildcard` segment
+    #[inline]
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Build URL of named route, see [`UrlBuilder::url_for`]
+    #[inline]
+    pub fn url_for(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String> {
+        self.urls.url_for(name, params, query)
+    }
+
+    /// State of the app or the router that serves this request, `None` when
+    /// it's not set or not a `T`
+    #[inline]
+    pub fn state<T: 'static>(&self) -> Option<&T> {
+        self.state.as_ref()?.downcast_ref()
+    }
+
+    /// Target host of the request
+    ///
+    /// Absolute-form and authority-form carry their own authority, the `host`
+    /// header must be ignored for them. Otherwise use the `host` header.
+    #[inline]
+    pub fn host(&self) -> Option<&str> {
+        self.uri.authority().or_else(|| self.headers.get("host"))
+    }
+
+    /// Body stream of the connection, `None` when it's read into
+    /// [`Request::body`] or taken already
+    ///
+    /// Framing is decoded underneath, the stream yields content only. Body
+    /// left unread is drained after the response.
+    #[inline]
+    pub fn take_body_stream(&mut self) -> Option<BodyStream> {
+        self.incoming.lock().ok()?.take()
+    }
+
+    /// Replace the body stream, see [`Request::take_body_stream`]
+    #[inline]
+    pub fn set_body_stream(&mut self, stream: BodyStream) {
+        self.incoming = Arc::new(Mutex::new(Some(stream)));
+    }
+
+    /// Body as stream, the body stream or [`Request::body`] when it's read
+    /// already
+    #[inline]
+    pub fn into_body_stream(mut self) -> BodyStream {
+        match self.take_body_stream() {
+            Some(stream) => stream,
+            None => stream::once(async move { Ok(self.body) }).boxed(),
+        }
+    }
+
+    /// Read the body stream into [`Request::body`] and return it
+    ///
+    /// Fail with [`Error::ContentTooLarge`] as soon as `content-length` or
+    /// the bytes read exceed [`Request::body_limit`].
+    pub async fn read_body(&mut self) -> Result<Bytes> {
+        if let Some(stream) = self.take_body_stream() {
+            let limit = self.body_limit;
+            let length = self
+                .headers
+                .get("content-length")
+                .and_then(|l| l.trim().parse::<usize>().ok());
+            if length.is_some_and(|l| l > limit) {
+                return Err(Error::ContentTooLarge(limit).into());
+            }
+            let body = stream
+                .try_fold(BytesMut::new(), |mut body, chunk| async move {
+                    if body.len() + chunk.len() > limit {
+                        return Err(Error::ContentTooLarge(limit).into());
+                    }
+                    body.extend_from_slice(&chunk);
+                    Ok(body)
+                })
+                .await?;
+            self.body = body.freeze();
+        }
+        Ok(self.body.clone())
+    }
+
+    /// Validate request target form against method and `host` header
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc9112#section-3.2
+    #[inline]
+    fn check_target(&self) -> Result<()> {
+        match (&self.uri, &self.method) {
+            (Uri::Authority(_), Method::Connect) => {}
+            (Uri::Authority(_), _) => bail!("authority-form is only allowed for CONNECT"),
+            (_, Method::Connect) => bail!("CONNECT requires authority-form"),
+            (Uri::Asterisk, Method::Options) => {}
+            (Uri::Asterisk, _) => bail!("asterisk-form is only allowed for OPTIONS"),
+            _ => {}
+        }
+        match self.headers.get_all("host").count() {
+            0 if self.version == "HTTP/1.1" => bail!("missing host header"),
+            0 | 1 => {}
+            _ => bail!("multiple host headers"),
+        }
+        // different lengths make the body boundary ambiguous
+        let mut content_len = self.headers.get_all("content-length");
+        if let Some(len) = content_len.next() {
+            if content_len.any(|l| l != len) {
+                bail!("conflicting content-length headers");
+            }
+            if !len.bytes().all(|b| b.is_ascii_digit()) || len.parse::<u64>().is_err() {
+                bail!("invalid content-length {len:?}");
+            }
+        }
+        // https://www.rfc-editor.org/rfc/rfc9112#section-6.1
+        if let Some(codings) = self.transfer_codings() {
+            if !codings
+                .last()
+                .is_some_and(|c| c.eq_ignore_ascii_case("chunked"))
+            {
+                bail!("request body must end with chunked transfer coding");
+            }
+            if self.headers.contains("content-length") {
+                bail!("both transfer-encoding and content-length");
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfer codings of every `Transfer-Encoding` header in order
+    #[inline]
+    fn transfer_codings(&self) -> Option<Vec<&str>> {
+        let codings = self
+            .headers
+            .get_all("transfer-encoding")
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        (!codings.is_empty() || self.headers.contains("transfer-encoding")).then_some(codings)
+    }
 }
 
 /// Read bytes from reader to string
@@ -108,32 +317,128 @@ where
     Ok((headers.clone(), reader))
 }
 
-/// Read client request body by it's content-length
-#[inline]
-pub async fn read_body<R>(mut reader: R, len: &str) -> Result<(Bytes, R)>
-where
-    R: AsyncRead + Unpin,
-{
-    let len: usize = len.parse().map_err(|e| anyhow!("{e}"))?;
-    let mut buffer = vec![0u8; len];
-    reader.read_exact(&mut buffer).await?;
-    let buffer = Bytes::copy_from_slice(&buffer);
-    Ok((buffer, reader))
+/// How the body of a request is delimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// Bytes of `Content-Length` left
+    Length(u64),
+    ChunkSize,
+    /// Bytes of current chunk left
+    ChunkData(u64),
+    /// `\r\n` after chunk data
+    ChunkEnd,
+    Trailers,
+    Done,
 }
 
-/// Pull all body into tokio::io::empty
-#[inline]
-pub async fn drop_body<R>(reader: R, len: Option<&str>) -> Result<()>
+/// Body of a request read from the connection, content is yielded without
+/// framing
+///
+/// Reading is cancel safe, it only awaits a single read into the buffer.
+pub(crate) struct BodyReader<R> {
+    reader: R,
+    buffer: BytesMut,
+    framing: Framing,
+}
+
+impl<R> BodyReader<R>
 where
     R: AsyncRead + Unpin,
 {
-    if let Some(len) = len {
-        let len: u64 = len.parse().map_err(|e| anyhow!("{e}"))?;
-        let mut r = reader.take(len);
-        let mut null = io::empty();
-        io::copy(&mut r, &mut null).await?;
+    /// Framing of `req` is validated when it's parsed
+    pub(crate) fn new(reader: R, req: &Request) -> Self {
+        let framing = match (req.transfer_codings(), req.headers.get("content-length")) {
+            (Some(_), _) => Framing::ChunkSize,
+            (None, Some(len)) => Framing::Length(len.parse().unwrap_or(0)),
+            (None, None) => Framing::Done,
+        };
+        Self {
+            reader,
+            buffer: BytesMut::new(),
+            framing,
+        }
+    }
+
+    /// The next piece of content, `None` at the end of body
+    pub(crate) async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+        loop {
+            match self.framing {
+                Framing::Done => return Ok(None),
+                Framing::Length(0) => self.framing = Framing::Done,
+                Framing::ChunkData(0) => self.framing = Framing::ChunkEnd,
+                Framing::Length(left) | Framing::ChunkData(left) if !self.buffer.is_empty() => {
+                    let len = self.buffer.len().min(left as usize);
+                    let left = left - len as u64;
+                    self.framing = match self.framing {
+                        Framing::Length(_) => Framing::Length(left),
+                        _ => Framing::ChunkData(left),
+                    };
+                    return Ok(Some(self.buffer.split_to(len).freeze()));
+                }
+                Framing::Length(_) | Framing::ChunkData(_) => self.fill().await?,
+                Framing::ChunkEnd if self.buffer.len() < 2 => self.fill().await?,
+                Framing::ChunkEnd => {
+                    if !self.buffer.starts_with(b"\r\n") {
+                        bail!("missing line break after chunk");
+                    }
+                    self.buffer.advance(2);
+                    self.framing = Framing::ChunkSize;
+                }
+                Framing::ChunkSize | Framing::Trailers => {
+                    let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") else {
+                        if self.buffer.len() > MAX_CHUNK_LINE {
+                            bail!("chunk line too long");
+                        }
+                        self.fill().await?;
+                        continue;
+                    };
+                    let line = self.buffer.split_to(end + 2);
+                    let line = std::str::from_utf8(&line[..end])?;
+                    self.framing = match self.framing {
+                        // trailers end with an empty line and are ignored
+                        Framing::Trailers if line.is_empty() => Framing::Done,
+                        Framing::Trailers => Framing::Trailers,
+                        // `1a;ext=value`, extensions are ignored
+                        _ => {
+                            let size = line.split(';').next().unwrap_or("").trim();
+                            // `from_str_radix` alone takes a sign, like `+5`
+                            let parsed = Some(size)
+                                .filter(|s| s.bytes().all(|b| b.is_ascii_hexdigit()))
+                                .and_then(|s| u64::from_str_radix(s, 16).ok());
+                            match parsed {
+                                Some(0) => Framing::Trailers,
+                                Some(size) => Framing::ChunkData(size),
+                                None => bail!("invalid chunk size {size:?}"),
+                            }
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /// Read and discard the rest of body up to `limit` bytes
+    ///
+    /// Returns whether the body ended.
+    pub(crate) async fn drain(&mut self, limit: usize) -> bool {
+        let mut drained = 0;
+        while drained <= limit {
+            match self.next_chunk().await {
+                Ok(Some(chunk)) => drained += chunk.len(),
+                Ok(None) => return true,
+                Err(_) => return false,
+            }
+        }
+        false
+    }
+
+    #[inline]
+    async fn fill(&mut self) -> Result<()> {
+        if self.reader.read_buf(&mut self.buffer).await? == 0 {
+            bail!("connection closed before end of body");
+        }
+        Ok(())
     }
-    Ok(())
 }
 
 // TODO: error handle
@@ -144,22 +449,22 @@ where
 ///
 /// ## Arguments
 ///
-/// - prev: (method, path, version)
+/// - prev: (method, uri, version)
 #[inline]
 fn fold_first_line(
-    mut prev: (String, PathBuf, String),
+    mut prev: (Method, Uri, String),
     (i, r): (usize, &[u8]),
-) -> Result<(String, PathBuf, String)> {
+) -> Result<(Method, Uri, String)> {
     let str = std::str::from_utf8(r)?;
     match i {
         0 => {
             // GET
-            prev.0 = str.to_owned();
+            prev.0 = Method::parse(str)?;
             anyhow::Ok(prev)
         }
         1 => {
-            // /v1/
-            prev.1 = PathBuf::from(str);
+            // /v1/ or http://host/v1/ or host:443 or *
+            prev.1 = Uri::parse(str)?;
             anyhow::Ok(prev)
         }
         2 => {
@@ -171,25 +476,118 @@ fn fold_first_line(
     }
 }
 
-/// Collect headers into hashmap
+/// Split header line `User-Agent: ua` into name and value
 ///
-/// ## Arguments
-///
-/// - prev: (key, value) for hashmap
+/// Whitespace between name and colon is rejected, whitespace around value
+/// is trimmed.
 #[inline]
-fn fold_headers(mut prev: (String, String), (i, h): (usize, &str)) -> Result<(String, String)> {
-    match i {
-        // User-Agent: ua
-        0 => {
-            // User-Agent
-            prev.0 = h.to_lowercase().to_owned();
-            anyhow::Ok(prev)
+pub(crate) fn parse_header(line: &str) -> Result<(&str, &str)> {
+    let Some((name, value)) = line.split_once(':') else {
+        bail!("invalid header line {line:?}");
+    };
+    if name.ends_with([' ', '\t']) {
+        bail!("whitespace before colon in header {name:?}");
+    }
+    Ok((name, value.trim_matches([' ', '\t'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Request of `headers` with `body` read by [`BodyReader`]
+    fn reader<'a>(headers: &str, body: &'a str) -> BodyReader<&'a [u8]> {
+        let head = format!("POST / HTTP/1.1\r\nHost: x\r\n{headers}\r\n\r\n");
+        let req = Request::parse_from_bytes(Bytes::from(head)).unwrap();
+        BodyReader::new(body.as_bytes(), &req)
+    }
+
+    async fn read_all<R: AsyncRead + Unpin>(mut reader: BodyReader<R>) -> Result<String> {
+        let mut body = vec![];
+        while let Some(chunk) = reader.next_chunk().await? {
+            body.extend_from_slice(&chunk);
         }
-        1 => {
-            // ua
-            prev.1 = h.to_owned();
-            anyhow::Ok(prev)
+        Ok(String::from_utf8(body)?)
+    }
+
+    #[tokio::test]
+    async fn read_content_length() {
+        let body = read_all(reader("Content-Length: 5", "helloNEXT")).await;
+        assert_eq!(body.unwrap(), "hello");
+        let body = read_all(reader("Content-Length: 0", "NEXT")).await;
+        assert_eq!(body.unwrap(), "");
+        assert!(read_all(reader("Content-Length: 9", "hello"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn read_chunked() {
+        let body = "5\r\nhello\r\n6;ext=\"a;b\"\r\n world\r\nA \r\n, chunked!\r\n\
+            0\r\nExpires: never\r\n\r\nNEXT";
+        let body = read_all(reader("Transfer-Encoding: chunked", body)).await;
+        assert_eq!(body.unwrap(), "hello world, chunked!");
+        let body = read_all(reader("Transfer-Encoding: chunked", "0\r\n\r\n")).await;
+        assert_eq!(body.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn reject_malformed_chunks() {
+        let long_line = "1".repeat(MAX_CHUNK_LINE + 1);
+        for body in [
+            "",
+            "\r\n",
+            "zz\r\nhello\r\n0\r\n\r\n",
+            "+5\r\nhello\r\n0\r\n\r\n",
+            "-5\r\nhello\r\n0\r\n\r\n",
+            "0x5\r\nhello\r\n0\r\n\r\n",
+            "10000000000000000\r\n",
+            "5\r\nhelloXX0\r\n\r\n",
+            "5\r\nhello world\r\n0\r\n\r\n",
+            "5\r\nhel",
+            "5\r\nhello\r\n",
+            "0\r\nExpires: never\r\n",
+            &long_line,
+        ] {
+            let read = read_all(reader("Transfer-Encoding: chunked", body)).await;
+            assert!(read.is_err(), "{body:?}");
         }
-        _ => bail!(""),
+    }
+
+    #[tokio::test]
+    async fn drain_up_to_limit() {
+        let body = "5\r\nhello\r\n5\r\nworld\r\n0\r\n\r\n";
+        assert!(reader("Transfer-Encoding: chunked", body).drain(10).await);
+        assert!(!reader("Transfer-Encoding: chunked", body).drain(4).await);
+        assert!(!reader("Content-Length: 9", "hello").drain(100).await);
+    }
+
+    #[tokio::test]
+    async fn read_body_up_to_limit() {
+        let stream = |chunks: &[&'static str]| {
+            let chunks = chunks.iter().map(|c| Ok(Bytes::from_static(c.as_bytes())));
+            stream::iter(chunks.collect::<Vec<_>>()).boxed()
+        };
+        let mut req = Request {
+            body_limit: 8,
+            ..Default::default()
+        };
+        req.set_body_stream(stream(&["1234", "5678"]));
+        assert_eq!(req.read_body().await.unwrap(), "12345678");
+
+        req.set_body_stream(stream(&["1234", "56789"]));
+        let err = req.read_body().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(Error::ContentTooLarge(8))
+        ));
+
+        req.headers.insert("content-length", "9").unwrap();
+        req.set_body_stream(stream(&[]));
+        let err = req.read_body().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(Error::ContentTooLarge(8))
+        ));
     }
 }
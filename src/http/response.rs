@@ -1,82 +1,286 @@
-use std::{collections::HashMap, fmt::Display};
-
 use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use log::error;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+pub use super::status::Status;
+use super::{extensions::Extensions, header::HeaderMap};
+use crate::error::Error;
+
+/// Body sent in pieces as they are produced, see [`Response::stream`]
+pub type BodyStream = BoxStream<'static, anyhow::Result<Bytes>>;
 
 pub struct Response {
-    pub headers: HashMap<String, String>,
+    pub headers: HeaderMap,
     pub body: Bytes,
     pub status: Status,
+    /// Sent with chunked transfer coding instead of `body`, or as is when
+    /// `Content-Length` is set
+    pub stream: Option<BodyStream>,
+    /// Typed values that handlers pass back to middleware, never sent
+    pub extensions: Extensions,
 }
 
 impl Default for Response {
     fn default() -> Self {
         Self {
-            headers: HashMap::new(),
+            headers: HeaderMap::new(),
             body: Bytes::new(),
             status: Status::Ok,
+            stream: None,
+            extensions: Extensions::new(),
         }
     }
 }
 
+impl Response {
+    /// Response with streaming body
+    #[inline]
+    pub fn stream(stream: BodyStream) -> Self {
+        Self {
+            stream: Some(stream),
+            ..Default::default()
+        }
+    }
+
+    /// Remove body for `HEAD` response but keep it's `Content-Length`
+    ///
+    /// The length of streaming body is unknown, it's sent as chunked. 1xx and
+    /// 204 get neither.
+    #[inline]
+    pub fn strip_body(&mut self) {
+        if !self.no_length() && !self.headers.contains("content-length") {
+            // both values are always valid
+            let _ = match self.stream.take() {
+                Some(_) => self.headers.insert("Transfer-Encoding", "chunked"),
+                None => self
+                    .headers
+                    .insert("Content-Length", &self.body.len().to_string()),
+            };
+        }
+        self.stream = None;
+        self.body = Bytes::new();
+    }
+}
+
+/// Build a response from handler's return value
+///
+/// Strings are sent as `text/plain`, bytes as `application/octet-stream`.
+/// `None` is 404 and `Err` is the error's response.
+///
+/// ```not_rust
+/// async fn created(req: Request) -> impl IntoResponse {
+///     (Status::Created, [("Location", "/users/1")], "created")
+/// }
+/// ```
 pub trait IntoResponse {
-    fn into_response(self) -> Vec<u8>;
+    fn into_response(self) -> Response;
 }
 
 impl IntoResponse for Response {
     #[inline]
-    fn into_response(self) -> Vec<u8> {
-        let headers = self
-            .headers
-            .into_iter()
-            .map(|(k, v)| format!("{k}: {v}\r\n"))
-            .flat_map(|s| s.into_bytes())
-            .collect::<Vec<_>>();
-        let headers = [headers, b"\r\n".to_vec()].concat();
-        let response = format!("HTTP/1.1 {}\r\n", self.status);
-        [response.as_bytes(), &headers, &self.body].concat()
+    fn into_response(self) -> Response {
+        self
     }
 }
 
-/* impl From<Response> for &[u8] {
-    fn from(value: Response) -> Self {
-        value.into_response().as_slice()
+impl IntoResponse for Status {
+    #[inline]
+    fn into_response(self) -> Response {
+        Response {
+            status: self,
+            ..Default::default()
+        }
     }
-} */
-impl From<Response> for Vec<u8> {
+}
+
+impl IntoResponse for &str {
+    #[inline]
+    fn into_response(self) -> Response {
+        with_content_type(
+            Bytes::copy_from_slice(self.as_bytes()),
+            "text/plain; charset=utf-8",
+        )
+    }
+}
+
+impl IntoResponse for String {
+    #[inline]
+    fn into_response(self) -> Response {
+        with_content_type(self.into(), "text/plain; charset=utf-8")
+    }
+}
+
+impl IntoResponse for Bytes {
+    #[inline]
+    fn into_response(self) -> Response {
+        with_content_type(self, "application/octet-stream")
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    #[inline]
+    fn into_response(self) -> Response {
+        with_content_type(self.into(), "application/octet-stream")
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (Status, T) {
     #[inline]
-    fn from(value: Response) -> Self {
-        value.into_response()
+    fn into_response(self) -> Response {
+        let mut res = self.1.into_response();
+        res.status = self.0;
+        res
     }
 }
 
-#[derive(Debug)]
-pub enum Status {
-    Ok,
-    InternalServer,
-    NotFound,
-    MethodNotAllowed,
-    BadRequest,
+impl<T: IntoResponse> IntoResponse for (Status, HeaderMap, T) {
+    #[inline]
+    fn into_response(self) -> Response {
+        let mut res = (self.0, self.2).into_response();
+        for (k, v) in &self.1 {
+            // values of `HeaderMap` are valid already
+            let _ = res.headers.append(k, v);
+        }
+        res
+    }
 }
 
-impl From<&Status> for &str {
+impl<T: IntoResponse, const N: usize> IntoResponse for (Status, [(&str, &str); N], T) {
+    /// Invalid headers result in 500
+    fn into_response(self) -> Response {
+        let mut res = (self.0, self.2).into_response();
+        for (k, v) in self.1 {
+            if let Err(err) = res.headers.insert(k, v) {
+                error!("build response failed {err}");
+                return Status::InternalServer.into_response();
+            }
+        }
+        res
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
     #[inline]
-    fn from(val: &Status) -> Self {
-        use Status::*;
-
-        match val {
-            Ok => "200 OK",
-            InternalServer => "500 Internal Server Error",
-            NotFound => "404 Not Found",
-            MethodNotAllowed => "405 Method Not Allowed",
-            BadRequest => "400 Bad Request",
+    fn into_response(self) -> Response {
+        match self {
+            Ok(res) => res.into_response(),
+            Err(err) => err.into_response(),
         }
     }
 }
 
-impl Display for Status {
+impl<T: IntoResponse> IntoResponse for Option<T> {
+    #[inline]
+    fn into_response(self) -> Response {
+        match self {
+            Some(res) => res.into_response(),
+            None => Status::NotFound.into_response(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    #[inline]
+    fn into_response(self) -> Response {
+        error!("{self}");
+        self.status().into_response()
+    }
+}
+
+#[inline]
+fn with_content_type(body: Bytes, mime: &str) -> Response {
+    let mut res = Response {
+        body,
+        ..Default::default()
+    };
+    // known mime types are valid values
+    let _ = res.headers.insert("Content-Type", mime);
+    res
+}
+
+impl Response {
+    /// 1xx and 204 have neither content nor it's length
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let status: &'static str = self.into();
-        write!(f, "{}", status)
+    fn no_length(&self) -> bool {
+        self.status.is_informational() || self.status == Status::NoContent
+    }
+
+    /// 304 has no content but may have it's length
+    #[inline]
+    fn no_content(&self) -> bool {
+        self.no_length() || self.status == Status::NotModified
+    }
+
+    /// Status line and headers, streaming body has no length
+    fn head(&self, streaming: bool) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\n", self.status).into_bytes();
+        for (k, v) in &self.headers {
+            head.extend(format!("{k}: {v}\r\n").into_bytes());
+        }
+        let framed =
+            self.headers.contains("content-length") || self.headers.contains("transfer-encoding");
+        if !self.no_length() && !framed {
+            match streaming {
+                true => head.extend(b"Transfer-Encoding: chunked\r\n"),
+                false => {
+                    head.extend(format!("Content-Length: {}\r\n", self.body.len()).into_bytes())
+                }
+            }
+        }
+        head.extend(b"\r\n");
+        head
+    }
+}
+
+/// Write response to client, streaming body is written as it's produced
+///
+/// Fails when the stream failed, the response is incomplete then and the
+/// connection must be closed.
+pub async fn write_response<W>(writer: &mut W, mut res: Response) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    match res.stream.take() {
+        Some(mut stream) if !res.no_content() => {
+            writer.write_all(&res.head(true)).await?;
+            let chunked = !res.headers.contains("content-length");
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                if chunk.is_empty() {
+                    continue;
+                }
+                if chunked {
+                    writer
+                        .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                        .await?;
+                    writer.write_all(&chunk).await?;
+                    writer.write_all(b"\r\n").await?;
+                } else {
+                    writer.write_all(&chunk).await?;
+                }
+                writer.flush().await?;
+            }
+            if chunked {
+                writer.write_all(b"0\r\n\r\n").await?;
+            }
+        }
+        _ => writer.write_all(&Vec::from(res)).await?,
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+impl From<Response> for Vec<u8> {
+    /// Serialize the response with status line and headers, streaming body
+    /// is dropped, see [`write_response`]
+    fn from(mut res: Response) -> Self {
+        res.stream = None;
+        let head = res.head(false);
+        if res.no_content() {
+            head
+        } else {
+            [head, res.body.to_vec()].concat()
+        }
     }
 }
@@ -0,0 +1,204 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+
+macro_rules! statuses {
+    ($($(#[$doc:meta])* $name:ident = $code:literal, $reason:literal;)+) => {
+        /// HTTP response status
+        ///
+        /// Every code of the IANA registry has it's own variant with the
+        /// canonical reason phrase, other codes are [`Status::Custom`].
+        ///
+        /// https://www.iana.org/assignments/http-status-codes
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum Status {
+            $(
+                #[doc = concat!("`", $code, " ", $reason, "`")]
+                $(#[$doc])*
+                $name,
+            )+
+            /// Unregistered code or registered code with a custom reason,
+            /// build it with [`Status::custom`]
+            Custom(u16, String),
+        }
+
+        impl Status {
+            /// Numeric status code
+            #[inline]
+            pub fn code(&self) -> u16 {
+                match self {
+                    $(Self::$name => $code,)+
+                    Self::Custom(code, _) => *code,
+                }
+            }
+
+            /// Reason phrase, empty for custom codes without one
+            #[inline]
+            pub fn reason(&self) -> &str {
+                match self {
+                    $(Self::$name => $reason,)+
+                    Self::Custom(_, reason) => reason,
+                }
+            }
+
+            /// Registered status of `code`
+            #[inline]
+            fn registered(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$name),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+statuses! {
+    Continue = 100, "Continue";
+    SwitchingProtocols = 101, "Switching Protocols";
+    Processing = 102, "Processing";
+    EarlyHints = 103, "Early Hints";
+
+    Ok = 200, "OK";
+    Created = 201, "Created";
+    Accepted = 202, "Accepted";
+    NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+    NoContent = 204, "No Content";
+    ResetContent = 205, "Reset Content";
+    PartialContent = 206, "Partial Content";
+    MultiStatus = 207, "Multi-Status";
+    AlreadyReported = 208, "Already Reported";
+    ImUsed = 226, "IM Used";
+
+    MultipleChoices = 300, "Multiple Choices";
+    MovedPermanently = 301, "Moved Permanently";
+    Found = 302, "Found";
+    SeeOther = 303, "See Other";
+    NotModified = 304, "Not Modified";
+    UseProxy = 305, "Use Proxy";
+    TemporaryRedirect = 307, "Temporary Redirect";
+    PermanentRedirect = 308, "Permanent Redirect";
+
+    BadRequest = 400, "Bad Request";
+    Unauthorized = 401, "Unauthorized";
+    PaymentRequired = 402, "Payment Required";
+    Forbidden = 403, "Forbidden";
+    NotFound = 404, "Not Found";
+    MethodNotAllowed = 405, "Method Not Allowed";
+    NotAcceptable = 406, "Not Acceptable";
+    ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+    RequestTimeout = 408, "Request Timeout";
+    Conflict = 409, "Conflict";
+    Gone = 410, "Gone";
+    LengthRequired = 411, "Length Required";
+    PreconditionFailed = 412, "Precondition Failed";
+    ContentTooLarge = 413, "Content Too Large";
+    UriTooLong = 414, "URI Too Long";
+    UnsupportedMediaType = 415, "Unsupported Media Type";
+    RangeNotSatisfiable = 416, "Range Not Satisfiable";
+    ExpectationFailed = 417, "Expectation Failed";
+    MisdirectedRequest = 421, "Misdirected Request";
+    UnprocessableContent = 422, "Unprocessable Content";
+    Locked = 423, "Locked";
+    FailedDependency = 424, "Failed Dependency";
+    TooEarly = 425, "Too Early";
+    UpgradeRequired = 426, "Upgrade Required";
+    PreconditionRequired = 428, "Precondition Required";
+    TooManyRequests = 429, "Too Many Requests";
+    RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+    UnavailableForLegalReasons = 451, "Unavailable For Legal Reasons";
+
+    InternalServer = 500, "Internal Server Error";
+    NotImplemented = 501, "Not Implemented";
+    BadGateway = 502, "Bad Gateway";
+    ServiceUnavailable = 503, "Service Unavailable";
+    GatewayTimeout = 504, "Gateway Timeout";
+    HttpVersionNotSupported = 505, "HTTP Version Not Supported";
+    VariantAlsoNegotiates = 506, "Variant Also Negotiates";
+    InsufficientStorage = 507, "Insufficient Storage";
+    LoopDetected = 508, "Loop Detected";
+    /// Obsoleted, kept for old clients
+    NotExtended = 510, "Not Extended";
+    NetworkAuthenticationRequired = 511, "Network Authentication Required";
+}
+
+impl Status {
+    /// Status of `code` with canonical reason, or without reason when the
+    /// code is not registered
+    ///
+    /// Fails when `code` is not three digits.
+    #[inline]
+    pub fn from_code(code: u16) -> Result<Self> {
+        if !(100..=999).contains(&code) {
+            bail!("invalid status code {code}");
+        }
+        Ok(Self::registered(code).unwrap_or(Self::Custom(code, String::new())))
+    }
+
+    /// Status with custom reason phrase
+    ///
+    /// Fails when `code` is not three digits or `reason` has control
+    /// characters other than tab.
+    #[inline]
+    pub fn custom(code: u16, reason: &str) -> Result<Self> {
+        if !(100..=999).contains(&code) {
+            bail!("invalid status code {code}");
+        }
+        if reason.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+            bail!("invalid reason of status {code}");
+        }
+        Ok(Self::Custom(code, reason.to_owned()))
+    }
+
+    /// 1xx
+    #[inline]
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.code())
+    }
+
+    /// 2xx
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.code())
+    }
+
+    /// 3xx
+    #[inline]
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.code())
+    }
+
+    /// 4xx
+    #[inline]
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.code())
+    }
+
+    /// 5xx
+    #[inline]
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.code())
+    }
+}
+
+impl TryFrom<u16> for Status {
+    type Error = anyhow::Error;
+
+    #[inline]
+    fn try_from(code: u16) -> Result<Self> {
+        Self::from_code(code)
+    }
+}
+
+impl Display for Status {
+    /// Status line without version, like `404 Not Found`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `Custom` may be built without `Status::custom`, never send a
+        // broken status line
+        let reason = self
+            .reason()
+            .chars()
+            .filter(|c| !c.is_ascii_control() || *c == '\t');
+        write!(f, "{} {}", self.code(), reason.collect::<String>())
+    }
+}
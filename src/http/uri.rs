@@ -0,0 +1,255 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Result};
+
+/// HTTP request target
+///
+/// https://www.rfc-editor.org/rfc/rfc9112#section-3.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Uri {
+    /// `/where?q=now`
+    Origin { path: String, query: Option<String> },
+    /// `http://www.example.org/pub/WWW/TheProject.html`, send to proxies
+    Absolute {
+        scheme: String,
+        authority: String,
+        path: String,
+        query: Option<String>,
+    },
+    /// `www.example.com:80`, only used by `CONNECT`
+    Authority(String),
+    /// `*`, only used by server-wide `OPTIONS`
+    Asterisk,
+}
+
+impl Default for Uri {
+    fn default() -> Self {
+        Self::Origin {
+            path: "/".to_owned(),
+            query: None,
+        }
+    }
+}
+
+impl Uri {
+    /// Parse request target from the second token of request line
+    #[inline]
+    pub fn parse(target: &str) -> Result<Self> {
+        if target.is_empty() {
+            bail!("empty request target");
+        }
+        if target.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+            bail!("invalid character in request target {target}");
+        }
+
+        // OPTIONS * HTTP/1.1
+        if target == "*" {
+            return Ok(Self::Asterisk);
+        }
+        // GET /where?q=now HTTP/1.1
+        if target.starts_with('/') {
+            let (path, query) = split_query(target);
+            return Ok(Self::Origin { path, query });
+        }
+        // GET http://www.example.org/pub HTTP/1.1
+        if let Some((scheme, rest)) = target.split_once("://") {
+            if !is_scheme(scheme) {
+                bail!("invalid scheme in request target {target}");
+            }
+            let (authority, path_query) = match rest.find(['/', '?']) {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, ""),
+            };
+            if authority.is_empty() {
+                bail!("missing authority in request target {target}");
+            }
+            let (path, query) = split_query(path_query);
+            // an empty path in absolute-form is the same as `/`
+            let path = if path.is_empty() {
+                "/".to_owned()
+            } else {
+                path
+            };
+            return Ok(Self::Absolute {
+                scheme: scheme.to_lowercase(),
+                authority: authority.to_owned(),
+                path,
+                query,
+            });
+        }
+        // CONNECT www.example.com:80 HTTP/1.1
+        match target.rsplit_once(':') {
+            Some((host, port))
+                if !host.is_empty()
+                    && !port.is_empty()
+                    && port.bytes().all(|b| b.is_ascii_digit())
+                    && !target.contains(['/', '?', '#', '@']) =>
+            {
+                Ok(Self::Authority(target.to_owned()))
+            }
+            _ => bail!("invalid request target {target}"),
+        }
+    }
+
+    /// The path used for routing
+    ///
+    /// Authority-form and asterisk-form have no path, both of them are
+    /// routed to `*`.
+    #[inline]
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Origin { path, .. } | Self::Absolute { path, .. } => path,
+            Self::Authority(_) | Self::Asterisk => "*",
+        }
+    }
+
+    /// Query string without leading `?`
+    #[inline]
+    pub fn query(&self) -> Option<&str> {
+        match self {
+            Self::Origin { query, .. } | Self::Absolute { query, .. } => query.as_deref(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn scheme(&self) -> Option<&str> {
+        match self {
+            Self::Absolute { scheme, .. } => Some(scheme),
+            _ => None,
+        }
+    }
+
+    /// Authority of absolute-form and authority-form, without userinfo
+    #[inline]
+    pub fn authority(&self) -> Option<&str> {
+        match self {
+            Self::Absolute { authority, .. } => {
+                Some(authority.rsplit_once('@').map_or(authority, |(_, a)| a))
+            }
+            Self::Authority(authority) => Some(authority),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Uri {
+    type Err = anyhow::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Origin { path, query } => {
+                write!(f, "{path}")?;
+                if let Some(q) = query {
+                    write!(f, "?{q}")?;
+                }
+                Ok(())
+            }
+            Self::Absolute {
+                scheme,
+                authority,
+                path,
+                query,
+            } => {
+                write!(f, "{scheme}://{authority}{path}")?;
+                if let Some(q) = query {
+                    write!(f, "?{q}")?;
+                }
+                Ok(())
+            }
+            Self::Authority(authority) => write!(f, "{authority}"),
+            Self::Asterisk => write!(f, "*"),
+        }
+    }
+}
+
+/// Split `/path?query` and drop the fragment
+#[inline]
+fn split_query(target: &str) -> (String, Option<String>) {
+    let target = target.split_once('#').map_or(target, |(t, _)| t);
+    match target.split_once('?') {
+        Some((path, query)) => (path.to_owned(), Some(query.to_owned())),
+        None => (target.to_owned(), None),
+    }
+}
+
+/// scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+#[inline]
+fn is_scheme(scheme: &str) -> bool {
+    let mut bytes = scheme.bytes();
+    bytes.next().is_some_and(|b| b.is_ascii_alphabetic())
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origin_form() {
+        let uri = Uri::parse("/where?q=now#top").unwrap();
+        assert_eq!(uri.path(), "/where");
+        assert_eq!(uri.query(), Some("q=now"));
+        assert_eq!(uri.authority(), None);
+        assert_eq!(uri.to_string(), "/where?q=now");
+        assert_eq!(Uri::parse("/").unwrap(), Uri::default());
+    }
+
+    #[test]
+    fn absolute_form() {
+        let uri = Uri::parse("HTTP://user@www.example.org:8080/pub?x=1").unwrap();
+        assert_eq!(uri.scheme(), Some("http"));
+        assert_eq!(uri.authority(), Some("www.example.org:8080"));
+        assert_eq!(uri.path(), "/pub");
+        assert_eq!(uri.query(), Some("x=1"));
+
+        let uri = Uri::parse("http://example.org?x").unwrap();
+        assert_eq!(uri.path(), "/");
+        assert_eq!(uri.query(), Some("x"));
+        assert_eq!(uri.to_string(), "http://example.org/?x");
+    }
+
+    #[test]
+    fn authority_and_asterisk_form() {
+        let uri = Uri::parse("www.example.com:443").unwrap();
+        assert_eq!(uri, Uri::Authority("www.example.com:443".to_owned()));
+        assert_eq!(uri.path(), "*");
+        assert_eq!(uri.authority(), Some("www.example.com:443"));
+
+        let uri = Uri::parse("*").unwrap();
+        assert_eq!(uri, Uri::Asterisk);
+        assert_eq!(uri.path(), "*");
+        assert_eq!(uri.query(), None);
+    }
+
+    #[test]
+    fn reject_malformed_targets() {
+        for target in [
+            "",
+            "/a b",
+            "/a\tb",
+            "/a\0",
+            "1http://x/",
+            "ht tp://x/",
+            "http:///path",
+            "http://",
+            "www.example.com",
+            "www.example.com:",
+            ":80",
+            "www.example.com:8a",
+            "user@www.example.com:80",
+            "www.example.com:80/x",
+            "?q",
+            "**",
+        ] {
+            assert!(Uri::parse(target).is_err(), "{target:?}");
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use bytes::Bytes;
+use futures::{future::BoxFuture, Stream, StreamExt};
+use log::error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+
+use crate::{
+    extract::{FromRequest, Rejection},
+    guard::essence,
+    request::Request,
+    response::{IntoResponse, Response, Status},
+};
+
+/// JSON request body or response
+///
+/// As extractor, the request must have `application/json` or `+json` content
+/// type, otherwise it's rejected with 415. Malformed JSON is rejected with
+/// 400 and JSON that doesn't fit `T` with 422.
+///
+/// ```not_rust
+/// async fn create(Json(user): Json<User>) -> impl IntoResponse {
+///     (Status::Created, Json(user))
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T> FromRequest for Json<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    type Rejection = Rejection;
+
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            if !req.headers.get("content-type").is_some_and(is_json) {
+                return Err(Rejection::new(
+                    Status::UnsupportedMediaType,
+                    "expected content type application/json",
+                ));
+            }
+            let body = Bytes::from_request(req).await?;
+            serde_json::from_slice(&body).map(Json).map_err(|e| {
+                let status = match e.classify() {
+                    Category::Data => Status::UnprocessableContent,
+                    Category::Io | Category::Syntax | Category::Eof => Status::BadRequest,
+                };
+                Rejection::new(status, format!("invalid JSON body: {e}"))
+            })
+        })
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => {
+                let mut res = Response {
+                    body: body.into(),
+                    ..Default::default()
+                };
+                let _ = res.headers.insert("Content-Type", "application/json");
+                res
+            }
+            Err(err) => {
+                error!("serialize JSON response failed {err}");
+                Status::InternalServer.into_response()
+            }
+        }
+    }
+}
+
+/// Stream values as newline delimited JSON, one value per line
+///
+/// Values are serialized as the stream produces them, so large result sets
+/// are never held in memory. A value that failed to serialize ends the
+/// response early.
+///
+/// ```not_rust
+/// async fn export() -> impl IntoResponse {
+///     NdJson(futures::stream::iter(rows))
+/// }
+/// ```
+pub struct NdJson<S>(pub S);
+
+impl<S> IntoResponse for NdJson<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn into_response(self) -> Response {
+        let lines = self.0.map(|value| {
+            let mut line = serde_json::to_vec(&value)?;
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        });
+        let mut res = Response::stream(Box::pin(lines));
+        let _ = res.headers.insert("Content-Type", "application/x-ndjson");
+        res
+    }
+}
+
+/// `application/json` or any `+json` media type
+#[inline]
+fn is_json(content_type: &str) -> bool {
+    let mime = essence(content_type).to_ascii_lowercase();
+    mime == "application/json"
+        || mime
+            .split_once('/')
+            .is_some_and(|(_, subtype)| subtype.ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::stream;
+
+    use super::*;
+
+    type Ages = HashMap<String, u8>;
+
+    async fn extract(content_type: &str, body: &str) -> Result<Ages, Rejection> {
+        let head = format!("POST / HTTP/1.1\r\nHost: x\r\nContent-Type: {content_type}\r\n\r\n");
+        let mut req = Request::parse_from_bytes(head.into()).unwrap();
+        req.body = Bytes::from(body.to_owned());
+        Json::<Ages>::from_request(req).await.map(|Json(ages)| ages)
+    }
+
+    #[tokio::test]
+    async fn accept_json_content_types() {
+        for content_type in [
+            "application/json",
+            "Application/JSON; charset=utf-8",
+            "application/problem+json",
+            "application/vnd.api+json",
+        ] {
+            let ages = extract(content_type, r#"{"ann": 42}"#).await.unwrap();
+            assert_eq!(ages["ann"], 42, "{content_type}");
+        }
+    }
+
+    #[tokio::test]
+    async fn reject_with_detailed_status() {
+        for (content_type, body, status) in [
+            ("text/plain", r#"{"ann": 42}"#, Status::UnsupportedMediaType),
+            (
+                "application/jsonx",
+                r#"{"ann": 42}"#,
+                Status::UnsupportedMediaType,
+            ),
+            (
+                "text/json+xml",
+                r#"{"ann": 42}"#,
+                Status::UnsupportedMediaType,
+            ),
+            ("application/json", r#"{ann: 42}"#, Status::BadRequest),
+            ("application/json", r#"{"ann": 42"#, Status::BadRequest),
+            ("application/json", "", Status::BadRequest),
+            (
+                "application/json",
+                r#"{"ann": "old"}"#,
+                Status::UnprocessableContent,
+            ),
+            (
+                "application/json",
+                r#"{"ann": 300}"#,
+                Status::UnprocessableContent,
+            ),
+            ("application/json", "[42]", Status::UnprocessableContent),
+        ] {
+            let rejection = extract(content_type, body).await.unwrap_err();
+            assert_eq!(rejection.status, status, "{content_type} {body}");
+        }
+    }
+
+    #[test]
+    fn respond_json() {
+        let res = Json(Ages::from([("ann".to_owned(), 42)])).into_response();
+        assert_eq!(res.headers.get("content-type"), Some("application/json"));
+        assert_eq!(res.body, r#"{"ann":42}"#);
+    }
+
+    #[tokio::test]
+    async fn frame_ndjson_lines() {
+        let rows = vec![vec![1, 2], vec![], vec![3]];
+        let mut res = NdJson(stream::iter(rows)).into_response();
+        assert_eq!(
+            res.headers.get("content-type"),
+            Some("application/x-ndjson")
+        );
+        let lines = res.stream.take().unwrap().collect::<Vec<_>>().await;
+        let lines = lines.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(lines, ["[1,2]\n", "[]\n", "[3]\n"]);
+
+        // map keys must be strings, the response ends at the failed value
+        let rows = vec![
+            HashMap::new(),
+            HashMap::from([(vec![1u8], 1u8)]),
+            HashMap::new(),
+        ];
+        let mut res = NdJson(stream::iter(rows)).into_response();
+        let lines = res.stream.take().unwrap().collect::<Vec<_>>().await;
+        assert_eq!(lines[0].as_ref().unwrap(), "{}\n");
+        assert!(lines[1].is_err());
+    }
+}
@@ -1,9 +1,25 @@
 pub mod error;
+pub mod extract;
+pub mod form;
+pub mod guard;
+pub mod handle;
+pub mod handler;
+pub mod host;
 pub mod http;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod middleware;
+pub mod multipart;
+pub mod router;
 pub mod server;
+pub mod table;
+#[cfg(feature = "tower")]
+pub mod tower;
+pub mod url;
 pub mod utils;
 
 pub use http::request;
 pub use http::response;
+pub use router::{Route, Router, TrailingSlash};
 pub use server::static_handler;
 pub use server::Rymo;
@@ -0,0 +1,113 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use futures::{future::BoxFuture, Future};
+
+use crate::{request::Request, response::Response, router::BoxHandler};
+
+/// Code runs around route handlers
+///
+/// Middleware can inspect and modify the request, return a response without
+/// calling `next`, or call `next` then post-process it's response.
+///
+/// Middleware attached to the app runs first, then the middleware of router
+/// groups from the outermost one, then the route's own middleware, and the
+/// response goes back in reverse order. Middleware of the app and host
+/// routers also runs for static assets and fallback responses.
+pub trait Middleware: Send + Sync + 'static {
+    /// Name shown in route table
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    fn call(
+        &self,
+        req: Request,
+        res: Response,
+        next: Next,
+    ) -> BoxFuture<'static, anyhow::Result<Response>>;
+}
+
+/// Shared middleware
+pub type BoxMiddleware = Arc<dyn Middleware>;
+
+/// The rest of middleware chain and the route handler
+pub struct Next {
+    middleware: VecDeque<BoxMiddleware>,
+    handler: BoxHandler,
+}
+
+impl Next {
+    /// ## Arguments
+    ///
+    /// - `middleware`: middleware chain, the outermost first
+    /// - `handler`: route handler
+    #[inline]
+    pub fn new(middleware: impl IntoIterator<Item = BoxMiddleware>, handler: BoxHandler) -> Self {
+        Self {
+            middleware: middleware.into_iter().collect(),
+            handler,
+        }
+    }
+
+    /// Call the next middleware, or the route handler at the end of chain
+    #[inline]
+    pub fn run(
+        mut self,
+        req: Request,
+        res: Response,
+    ) -> BoxFuture<'static, anyhow::Result<Response>> {
+        match self.middleware.pop_front() {
+            Some(middleware) => middleware.call(req, res, self),
+            None => (self.handler)(req, res),
+        }
+    }
+}
+
+/// Middleware built from a function, see [`from_fn`]
+pub struct FnMiddleware<F> {
+    name: String,
+    f: F,
+}
+
+impl<F, Fut> Middleware for FnMiddleware<F>
+where
+    F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<Response>> + Send + 'static,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    fn call(
+        &self,
+        req: Request,
+        res: Response,
+        next: Next,
+    ) -> BoxFuture<'static, anyhow::Result<Response>> {
+        Box::pin((self.f)(req, res, next))
+    }
+}
+
+/// Build middleware from an async function
+///
+/// ```not_rust
+/// async fn logger(req: Request, res: Response, next: Next) -> Result<Response> {
+///     info!("{} {}", req.method, req.path());
+///     next.run(req, res).await
+/// }
+///
+/// app.layer(from_fn("logger", logger)).await;
+/// ```
+#[inline]
+pub fn from_fn<F, Fut>(name: &str, f: F) -> FnMiddleware<F>
+where
+    F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<Response>> + Send + 'static,
+{
+    FnMiddleware {
+        name: name.to_owned(),
+        f,
+    }
+}
@@ -0,0 +1,729 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures::{future::BoxFuture, StreamExt};
+use percent_encoding::percent_decode_str;
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+    extract::{FromRequest, Rejection},
+    guard::essence,
+    http::{header::HeaderMap, request::parse_header},
+    request::Request,
+    response::{BodyStream, Status},
+};
+
+/// Size of headers of a single part
+const MAX_PART_HEADERS: usize = 8 * 1024;
+
+/// Limits of a multipart body, see [`Multipart::with_limits`]
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Bytes of a single field, exceeded is 413
+    pub field_size: usize,
+    /// Bytes of the whole body, include boundaries and headers, exceeded is
+    /// 413
+    pub total_size: usize,
+    /// File parts larger than this are spooled to `spool_dir` by
+    /// [`Field::spool`]
+    pub memory_size: usize,
+    pub spool_dir: PathBuf,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            field_size: 16 * 1024 * 1024,
+            total_size: 64 * 1024 * 1024,
+            memory_size: 256 * 1024,
+            spool_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Where the parser is in the body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Skip preamble or rest of a field until the next delimiter
+    Boundary,
+    /// Right after a delimiter, `--` ends the body
+    Delimited,
+    Headers,
+    /// Inside the body of the current field
+    Body,
+    Done,
+}
+
+/// `multipart/form-data` body parsed part by part as it arrives
+///
+/// Parts must be read in order, reading the next field skips the rest of the
+/// current one. Errors are rejections, so handlers may return them with `?`.
+///
+/// Routes of handlers taking it stream their body, only [`Limits`] bound the
+/// upload. When the body was read already, like by [`Request::read_body`] in
+/// middleware, it's parsed from [`Request::body`] which is capped by
+/// [`Request::body_limit`].
+///
+/// ```not_rust
+/// async fn upload(mut multipart: Multipart) -> Result<String, Rejection> {
+///     while let Some(field) = multipart.next_field().await? {
+///         if field.file_name().is_some() {
+///             let part = field.spool().await?;
+///             // move the spooled file before it's removed
+///         }
+///     }
+///     Ok("uploaded".to_owned())
+/// }
+/// ```
+pub struct Multipart {
+    stream: BodyStream,
+    buffer: BytesMut,
+    /// `\r\n--boundary`
+    delimiter: Vec<u8>,
+    state: State,
+    limits: Limits,
+    /// Bytes received from the stream
+    received: usize,
+}
+
+impl Multipart {
+    /// Parse `stream` with `boundary` of the content type
+    pub fn new(stream: BodyStream, boundary: &str) -> Self {
+        Self {
+            stream,
+            // the first delimiter may start the body without line break
+            buffer: BytesMut::from(&b"\r\n"[..]),
+            delimiter: [b"\r\n--", boundary.as_bytes()].concat(),
+            state: State::Boundary,
+            limits: Limits::default(),
+            received: 0,
+        }
+    }
+
+    /// Replace default limits, must be called before reading fields
+    #[inline]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The next part, `None` after the closing delimiter
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, Rejection> {
+        loop {
+            match self.state {
+                State::Done => return Ok(None),
+                State::Boundary | State::Body => match find(&self.buffer, &self.delimiter) {
+                    Some(i) => {
+                        self.buffer.advance(i + self.delimiter.len());
+                        self.state = State::Delimited;
+                    }
+                    None => {
+                        // keep a partial delimiter at the end
+                        let keep = self.delimiter.len() - 1;
+                        if self.buffer.len() > keep {
+                            self.buffer.advance(self.buffer.len() - keep);
+                        }
+                        self.fill().await?;
+                    }
+                },
+                State::Delimited => {
+                    if self.buffer.starts_with(b"--") {
+                        // epilogue is ignored
+                        self.state = State::Done;
+                        return Ok(None);
+                    }
+                    match find(&self.buffer, b"\r\n") {
+                        Some(i) => {
+                            // transport padding before line break
+                            if !self.buffer[..i].iter().all(|b| matches!(b, b' ' | b'\t')) {
+                                return Err(Rejection::bad_request(
+                                    "invalid multipart: garbage after boundary",
+                                ));
+                            }
+                            self.buffer.advance(i + 2);
+                            self.state = State::Headers;
+                        }
+                        None if self.buffer.len() > MAX_PART_HEADERS => {
+                            return Err(Rejection::bad_request(
+                                "invalid multipart: garbage after boundary",
+                            ));
+                        }
+                        None => self.fill().await?,
+                    }
+                }
+                State::Headers => {
+                    let end = match self.buffer.starts_with(b"\r\n") {
+                        true => Some(0),
+                        false => find(&self.buffer, b"\r\n\r\n").map(|i| i + 2),
+                    };
+                    match end {
+                        Some(end) => {
+                            let headers = parse_headers(&self.buffer[..end])?;
+                            self.buffer.advance(end + 2);
+                            self.state = State::Body;
+                            return Ok(Some(Field::new(self, headers)));
+                        }
+                        None if self.buffer.len() > MAX_PART_HEADERS => {
+                            return Err(Rejection::new(
+                                Status::RequestHeaderFieldsTooLarge,
+                                "multipart part headers too large",
+                            ));
+                        }
+                        None => self.fill().await?,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Append the next chunk of stream to buffer
+    ///
+    /// Fails when the stream ends before the closing delimiter.
+    async fn fill(&mut self) -> Result<(), Rejection> {
+        match self.stream.next().await {
+            Some(Ok(chunk)) => {
+                self.received += chunk.len();
+                if self.received > self.limits.total_size {
+                    return Err(Rejection::new(
+                        Status::ContentTooLarge,
+                        format!("multipart body exceeds {} bytes", self.limits.total_size),
+                    ));
+                }
+                self.buffer.extend_from_slice(&chunk);
+                Ok(())
+            }
+            Some(Err(err)) => Err(Rejection::bad_request(format!(
+                "read multipart body failed {err}"
+            ))),
+            None => Err(Rejection::bad_request(
+                "invalid multipart: body ends before closing boundary",
+            )),
+        }
+    }
+}
+
+/// Multipart body, reject with 415 for other content types and 400 without
+/// boundary
+impl FromRequest for Multipart {
+    type Rejection = Rejection;
+
+    fn from_request(req: Request) -> BoxFuture<'static, Result<Self, Self::Rejection>> {
+        Box::pin(async move {
+            let content_type = req.headers.get("content-type").unwrap_or("");
+            if !essence(content_type).eq_ignore_ascii_case("multipart/form-data") {
+                return Err(Rejection::new(
+                    Status::UnsupportedMediaType,
+                    "expected content type multipart/form-data",
+                ));
+            }
+            let boundary = params(content_type)
+                .find(|(k, _)| k.eq_ignore_ascii_case("boundary"))
+                .map(|(_, v)| v)
+                .filter(|b| (1..=70).contains(&b.len()))
+                .ok_or_else(|| Rejection::bad_request("invalid multipart: missing boundary"))?;
+            Ok(Self::new(req.into_body_stream(), &boundary))
+        })
+    }
+
+    #[inline]
+    fn streams_body() -> bool {
+        true
+    }
+}
+
+/// A part of [`Multipart`], it's body is read in chunks
+pub struct Field<'a> {
+    multipart: &'a mut Multipart,
+    headers: HeaderMap,
+    name: Option<String>,
+    file_name: Option<String>,
+    /// Bytes of body read so far
+    size: usize,
+}
+
+impl<'a> Field<'a> {
+    fn new(multipart: &'a mut Multipart, headers: HeaderMap) -> Self {
+        let disposition = headers.get("content-disposition").unwrap_or("");
+        let mut name = None;
+        let mut file_name = None;
+        let mut file_name_ext = None;
+        for (k, v) in params(disposition) {
+            match k.to_ascii_lowercase().as_str() {
+                "name" => name = Some(v),
+                "filename" => file_name = Some(v),
+                // RFC 5987 `UTF-8''caf%C3%A9.txt`, preferred over `filename`
+                "filename*" => {
+                    file_name_ext = v
+                        .split_once("''")
+                        .and_then(|(_, v)| percent_decode_str(v).decode_utf8().ok())
+                        .map(|v| v.into_owned())
+                }
+                _ => {}
+            }
+        }
+        Self {
+            multipart,
+            headers,
+            name,
+            file_name: file_name_ext.or(file_name),
+            size: 0,
+        }
+    }
+
+    /// Headers of this part
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// `name` of `Content-Disposition`
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// `filename` of `Content-Disposition`, set for file uploads
+    ///
+    /// It's sent by client, never use it as a path without sanitizing.
+    #[inline]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("content-type")
+    }
+
+    /// The next chunk of body, `None` at the end of this part
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, Rejection> {
+        let multipart = &mut *self.multipart;
+        loop {
+            if multipart.state != State::Body {
+                return Ok(None);
+            }
+            let delimiter = &multipart.delimiter;
+            let chunk = match find(&multipart.buffer, delimiter) {
+                Some(i) => {
+                    let chunk = multipart.buffer.split_to(i).freeze();
+                    multipart.buffer.advance(delimiter.len());
+                    multipart.state = State::Delimited;
+                    chunk
+                }
+                // keep a partial delimiter at the end
+                None if multipart.buffer.len() >= delimiter.len() => {
+                    let len = multipart.buffer.len() - (delimiter.len() - 1);
+                    multipart.buffer.split_to(len).freeze()
+                }
+                None => Bytes::new(),
+            };
+            if chunk.is_empty() {
+                if multipart.state == State::Body {
+                    multipart.fill().await?;
+                }
+                continue;
+            }
+            self.size += chunk.len();
+            if self.size > multipart.limits.field_size {
+                return Err(Rejection::new(
+                    Status::ContentTooLarge,
+                    format!(
+                        "multipart field {} exceeds {} bytes",
+                        self.name.as_deref().unwrap_or(""),
+                        multipart.limits.field_size
+                    ),
+                ));
+            }
+            return Ok(Some(chunk));
+        }
+    }
+
+    /// Whole body of this part in memory
+    pub async fn bytes(mut self) -> Result<Bytes, Rejection> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Whole body of this part as UTF-8 text, 400 when it's not
+    pub async fn text(self) -> Result<String, Rejection> {
+        let name = self.name.clone().unwrap_or_default();
+        String::from_utf8(self.bytes().await?.to_vec()).map_err(|e| {
+            Rejection::bad_request(format!("multipart field {name} is not UTF-8: {e}"))
+        })
+    }
+
+    /// Read this part, a file part larger than [`Limits::memory_size`] is
+    /// written to a temporary file under [`Limits::spool_dir`]
+    pub async fn spool(mut self) -> Result<Part, Rejection> {
+        let memory_size = self.multipart.limits.memory_size;
+        let mut body = BytesMut::new();
+        let mut spooled = None;
+        while let Some(chunk) = self.chunk().await? {
+            match &mut spooled {
+                Some((_, file)) => write_spool(file, &chunk).await?,
+                None if self.file_name.is_some() && body.len() + chunk.len() > memory_size => {
+                    let (temp, mut file) = TempFile::create(&self.multipart.limits.spool_dir)
+                        .await
+                        .map_err(spool_failed)?;
+                    write_spool(&mut file, &body).await?;
+                    write_spool(&mut file, &chunk).await?;
+                    body.clear();
+                    spooled = Some((temp, file));
+                }
+                None => body.extend_from_slice(&chunk),
+            }
+        }
+        let data = match spooled {
+            Some((mut temp, mut file)) => {
+                file.flush().await.map_err(spool_failed)?;
+                temp.size = self.size as u64;
+                PartData::File(temp)
+            }
+            None => PartData::Memory(body.freeze()),
+        };
+        Ok(Part {
+            headers: self.headers,
+            name: self.name,
+            file_name: self.file_name,
+            data,
+        })
+    }
+}
+
+/// A part read by [`Field::spool`]
+#[derive(Debug)]
+pub struct Part {
+    pub headers: HeaderMap,
+    pub name: Option<String>,
+    pub file_name: Option<String>,
+    pub data: PartData,
+}
+
+#[derive(Debug)]
+pub enum PartData {
+    Memory(Bytes),
+    File(TempFile),
+}
+
+/// Spooled file part, removed when dropped unless persisted
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    size: u64,
+}
+
+impl TempFile {
+    /// Create a file with unique name in `dir`
+    async fn create(dir: &Path) -> std::io::Result<(Self, fs::File)> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        loop {
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = dir.join(format!("rymo-{}-{n}.part", std::process::id()));
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => return Ok((Self { path, size: 0 }, file)),
+                // left by an earlier process with the same id
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Move the file to `to`, it's kept after then
+    pub async fn persist(self, to: impl AsRef<Path>) -> std::io::Result<()> {
+        let to = to.as_ref();
+        if fs::rename(&self.path, to).await.is_err() {
+            // rename fails across file systems
+            fs::copy(&self.path, to).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // already moved when persisted
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[inline]
+async fn write_spool(file: &mut fs::File, chunk: &[u8]) -> Result<(), Rejection> {
+    file.write_all(chunk).await.map_err(spool_failed)
+}
+
+#[inline]
+fn spool_failed(err: std::io::Error) -> Rejection {
+    Rejection::new(
+        Status::InternalServer,
+        format!("spool multipart field failed {err}"),
+    )
+}
+
+/// Parse part headers, lines end with `\r\n`
+fn parse_headers(block: &[u8]) -> Result<HeaderMap, Rejection> {
+    let invalid = |e: anyhow::Error| Rejection::bad_request(format!("invalid multipart: {e}"));
+    let block = std::str::from_utf8(block)
+        .map_err(|e| Rejection::bad_request(format!("invalid multipart: {e}")))?;
+    let mut headers = HeaderMap::new();
+    for line in block.split("\r\n").filter(|l| !l.is_empty()) {
+        let (k, v) = parse_header(line).map_err(invalid)?;
+        headers.append(k, v).map_err(invalid)?;
+    }
+    Ok(headers)
+}
+
+/// Parameters after the first `;` of a header value, quoted values are
+/// unquoted
+///
+/// `form-data; name="a;b"; filename=x` is `name = a;b` and `filename = x`
+fn params(value: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    let mut parts = vec![];
+    let mut quoted = false;
+    let mut start = 0;
+    let mut chars = value.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                chars.next();
+            }
+            ';' if !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().skip(1).filter_map(|part| {
+        let (k, v) = part.split_once('=')?;
+        let v = v.trim();
+        let v = match v.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => {
+                let mut unquoted = String::with_capacity(quoted.len());
+                let mut chars = quoted.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => unquoted.extend(chars.next()),
+                        c => unquoted.push(c),
+                    }
+                }
+                unquoted
+            }
+            None => v.to_owned(),
+        };
+        Some((k.trim().to_owned(), v))
+    })
+}
+
+#[inline]
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    /// Multipart of boundary `xyz` with `body` streamed `size` bytes a chunk
+    fn multipart(body: &[u8], size: usize) -> Multipart {
+        let chunks = body
+            .chunks(size)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect::<Vec<_>>();
+        Multipart::new(stream::iter(chunks).boxed(), "xyz")
+    }
+
+    /// `name`, `filename` and text of every field
+    async fn fields(
+        mut multipart: Multipart,
+    ) -> Result<Vec<(String, Option<String>, String)>, Rejection> {
+        let mut fields = vec![];
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.name().unwrap_or("").to_owned();
+            let file_name = field.file_name().map(str::to_owned);
+            fields.push((name, file_name, field.text().await?));
+        }
+        Ok(fields)
+    }
+
+    async fn rejection(body: &str, limits: Limits) -> Rejection {
+        fields(multipart(body.as_bytes(), 5).with_limits(limits))
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn parse_fields_split_anywhere() {
+        let body = "preamble\r\n--xyz\r\n\
+            Content-Disposition: form-data; name=\"a;b\"\r\n\r\n\
+            hello\r\n--xyz  \r\n\
+            Content-Disposition: form-data; name=file; filename=\"x.txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            line\r\n--xy\r\n--xyz\r\n\
+            Content-Disposition: form-data; name=doc; filename=x; \
+            filename*=UTF-8''caf%C3%A9.txt\r\n\r\n\
+            \r\n--xyz--\r\nepilogue";
+        let expected = vec![
+            ("a;b".to_owned(), None, "hello".to_owned()),
+            (
+                "file".to_owned(),
+                Some("x.txt".to_owned()),
+                "line\r\n--xy".to_owned(),
+            ),
+            (
+                "doc".to_owned(),
+                Some("caf\u{e9}.txt".to_owned()),
+                String::new(),
+            ),
+        ];
+        for size in [1, 2, 3, 7, 64, body.len()] {
+            let parsed = fields(multipart(body.as_bytes(), size)).await.unwrap();
+            assert_eq!(parsed, expected, "chunk size {size}");
+        }
+    }
+
+    #[tokio::test]
+    async fn skip_unread_fields() {
+        let body = "--xyz\r\nContent-Disposition: form-data; name=a\r\n\r\nskipped\r\n\
+            --xyz\r\nContent-Disposition: form-data; name=b\r\n\r\nread\r\n--xyz--";
+        let mut multipart = multipart(body.as_bytes(), 4);
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), Some("a"));
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(field.text().await.unwrap(), "read");
+        assert!(multipart.next_field().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reject_malformed_bodies() {
+        let part = "Content-Disposition: form-data; name=a\r\n\r\nhello";
+        for body in [
+            String::new(),
+            "no boundary at all".to_owned(),
+            format!("--xyz\r\n{part}"),
+            format!("--xyz\r\n{part}\r\n--xyz"),
+            format!("--xyzjunk\r\n{part}\r\n--xyz--"),
+            "--xyz\r\nbroken\r\n\r\nhello\r\n--xyz--".to_owned(),
+            "--xyz\r\nName : a\r\n\r\nhello\r\n--xyz--".to_owned(),
+        ] {
+            let rejection = rejection(&body, Limits::default()).await;
+            assert_eq!(rejection.status, Status::BadRequest, "{body:?}");
+        }
+
+        let body = format!("--xyz\r\nX: {}\r\n\r\nhello\r\n--xyz--", "a".repeat(9000));
+        assert_eq!(
+            rejection(&body, Limits::default()).await.status,
+            Status::RequestHeaderFieldsTooLarge
+        );
+    }
+
+    #[tokio::test]
+    async fn enforce_limits() {
+        let body = "--xyz\r\nContent-Disposition: form-data; name=a\r\n\r\nhello\r\n--xyz--";
+        let field_size = Limits {
+            field_size: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            rejection(body, field_size).await.status,
+            Status::ContentTooLarge
+        );
+        let total_size = Limits {
+            total_size: body.len() - 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            rejection(body, total_size).await.status,
+            Status::ContentTooLarge
+        );
+        let exact = Limits {
+            field_size: 5,
+            total_size: body.len(),
+            ..Default::default()
+        };
+        assert!(fields(multipart(body.as_bytes(), 5).with_limits(exact))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn spool_large_file_parts() {
+        let body = "--xyz\r\nContent-Disposition: form-data; name=f; filename=a.txt\r\n\r\n\
+            hello world\r\n--xyz\r\nContent-Disposition: form-data; name=t\r\n\r\n\
+            large text\r\n--xyz--";
+        let limits = Limits {
+            memory_size: 4,
+            ..Default::default()
+        };
+        let mut multipart = multipart(body.as_bytes(), 3).with_limits(limits);
+
+        let part = multipart.next_field().await.unwrap().unwrap().spool().await;
+        let PartData::File(file) = part.unwrap().data else {
+            panic!("file part is not spooled");
+        };
+        assert_eq!(file.size(), 11);
+        assert_eq!(std::fs::read(file.path()).unwrap(), b"hello world");
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+
+        let part = multipart.next_field().await.unwrap().unwrap().spool().await;
+        assert!(matches!(part.unwrap().data, PartData::Memory(b) if b == "large text"));
+    }
+
+    #[tokio::test]
+    async fn extract_boundary() {
+        let extract = |content_type: &str| {
+            let head =
+                format!("POST / HTTP/1.1\r\nHost: x\r\nContent-Type: {content_type}\r\n\r\n");
+            let req = Request::parse_from_bytes(Bytes::from(head)).unwrap();
+            Multipart::from_request(req)
+        };
+        assert!(extract("multipart/form-data; boundary=xyz").await.is_ok());
+        assert!(extract("Multipart/Form-Data; boundary=\"a b\"")
+            .await
+            .is_ok());
+        for (content_type, status) in [
+            ("multipart/form-data", Status::BadRequest),
+            ("multipart/form-data; boundary=", Status::BadRequest),
+            (
+                &format!("multipart/form-data; boundary={}", "b".repeat(71)),
+                Status::BadRequest,
+            ),
+            (
+                "multipart/mixed; boundary=xyz",
+                Status::UnsupportedMediaType,
+            ),
+            ("text/plain", Status::UnsupportedMediaType),
+        ] {
+            let rejection = extract(content_type).await.err().unwrap();
+            assert_eq!(rejection.status, status, "{content_type}");
+        }
+        assert!(<Multipart as FromRequest>::streams_body());
+    }
+}
@@ -0,0 +1,1041 @@
+use std::{
+    any::{type_name, Any},
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Result};
+use futures::future::BoxFuture;
+use log::error;
+use percent_encoding::percent_decode_str;
+
+use crate::{
+    guard::Guard,
+    handler::Handler,
+    http::method::Method,
+    middleware::{BoxMiddleware, Middleware},
+    request::Request,
+    response::{Response, Status},
+    table::RouteTable,
+    url::UrlBuilder,
+};
+
+/// Type erased route handler
+pub type BoxHandler =
+    Arc<dyn Fn(Request, Response) -> BoxFuture<'static, anyhow::Result<Response>> + Send + Sync>;
+
+/// Type erased app or router state, see [`crate::extract::State`]
+pub type BoxState = Arc<dyn Any + Send + Sync>;
+
+/// Registries routes
+///
+/// ```not_rust
+/// route_path : {
+///     http_method: [candidate_route]
+/// }
+/// ```
+///
+/// Route path can contain `:param` segments and a trailing `*wildcard`
+/// segment, like `/users/:id` and `/files/*path`.
+pub type Routes = HashMap<String, MethodRoutes>;
+/// Candidate routes of every method for the same path
+pub type MethodRoutes = HashMap<Method, Vec<Route>>;
+/// Path parameters, `(key, value)`
+pub type Params = Vec<(String, String)>;
+/// Static assets routes
+///
+/// ```not_rust
+/// route_path : assets_path
+/// ```
+pub type AssetsRoutes = BTreeMap<String, PathBuf>;
+
+/// A route's handler with it's method, path and guards
+#[derive(Clone)]
+pub struct Route {
+    pub method: Method,
+    pub path: String,
+    pub handler: BoxHandler,
+    /// All guards must match before the handler is called
+    pub guards: Vec<Guard>,
+    /// Name for building URL, see [`UrlBuilder::url_for`]
+    pub name: Option<String>,
+    /// Route's own middleware and middleware of router groups it nested in,
+    /// the outermost first
+    pub middleware: Vec<BoxMiddleware>,
+    /// Prefix of nested routers, see [`Request::nest_path`]
+    pub nest_path: String,
+    /// Hand the body to the handler as stream instead of reading it first,
+    /// see [`Request::take_body_stream`]
+    pub stream_body: bool,
+    /// State of the router the route nested or merged from, the state of
+    /// the serving router is used when it's `None`
+    pub state: Option<BoxState>,
+}
+
+impl Route {
+    /// ## Arguments
+    ///
+    /// - `method`: the request method, matched case-sensitively
+    /// - `path`: registry route's path
+    /// - `handler`: route handler
+    #[inline]
+    pub fn new<H, T>(method: Method, path: &str, handler: H) -> Self
+    where
+        H: Handler<T>,
+    {
+        Self {
+            method,
+            path: path.to_owned(),
+            handler: box_handler(handler),
+            guards: vec![],
+            name: None,
+            middleware: vec![],
+            nest_path: String::new(),
+            stream_body: H::streams_body(),
+            state: None,
+        }
+    }
+
+    /// Attach middleware to this route only, the first attached runs first
+    #[inline]
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Name the route for building URL
+    #[inline]
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Add a guard, see [`crate::guard`]
+    #[inline]
+    pub fn guard(mut self, guard: Guard) -> Self {
+        self.guards.push(guard);
+        self
+    }
+
+    /// Stream the request body to the handler, guards see an empty body
+    ///
+    /// Handlers taking an extractor that reads the stream, like
+    /// [`crate::multipart::Multipart`], stream without it.
+    #[inline]
+    pub fn stream_body(mut self) -> Self {
+        self.stream_body = true;
+        self
+    }
+
+    /// Find the first guard that rejects `req`
+    #[inline]
+    pub fn reject(&self, req: &Request) -> Option<&Guard> {
+        self.guards.iter().find(|g| !g.check(req))
+    }
+}
+
+/// How `/docs` and `/docs/` are matched for routes and static assets
+///
+/// The canonical path of a route is the registered one, the canonical path
+/// of static directory ends with `/` and static file doesn't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Only the canonical path matches
+    Strict,
+    /// Only the canonical path of routes matches, static directory serves
+    /// it's index with or without the slash like before the policy existed
+    ///
+    /// Relative links of an index served without the slash resolve against
+    /// the parent directory, prefer [`TrailingSlash::Redirect`] for sites.
+    #[default]
+    Legacy,
+    /// Redirect to the canonical path with 301
+    Redirect,
+    /// Redirect to the canonical path with 308, keep request method and body
+    PermanentRedirect,
+    /// Both paths match
+    Ignore,
+}
+
+impl TrailingSlash {
+    #[inline]
+    pub fn redirect_status(&self) -> Option<Status> {
+        match self {
+            Self::Redirect => Some(Status::MovedPermanently),
+            Self::PermanentRedirect => Some(Status::PermanentRedirect),
+            _ => None,
+        }
+    }
+}
+
+/// Handlers for responses that no route produced
+///
+/// Fallback handlers receive the original request and a response with the
+/// status already set, `Allow` header is also set for 405.
+#[derive(Default, Clone)]
+pub struct Fallbacks {
+    /// 404
+    pub not_found: Option<BoxHandler>,
+    /// 405
+    pub method_not_allowed: Option<BoxHandler>,
+    /// 400, the request is empty when it can't be parsed
+    pub bad_request: Option<BoxHandler>,
+    /// 500, the request body is not available
+    pub internal_error: Option<BoxHandler>,
+}
+
+impl Fallbacks {
+    #[inline]
+    pub fn get(&self, status: &Status) -> Option<&BoxHandler> {
+        match status {
+            Status::NotFound => self.not_found.as_ref(),
+            Status::MethodNotAllowed => self.method_not_allowed.as_ref(),
+            Status::BadRequest => self.bad_request.as_ref(),
+            Status::InternalServer => self.internal_error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Keep own handlers, take the missing ones from `other`
+    #[inline]
+    fn or(self, other: Fallbacks) -> Self {
+        Self {
+            not_found: self.not_found.or(other.not_found),
+            method_not_allowed: self.method_not_allowed.or(other.method_not_allowed),
+            bad_request: self.bad_request.or(other.bad_request),
+            internal_error: self.internal_error.or(other.internal_error),
+        }
+    }
+
+    #[inline]
+    fn map(self, f: impl Fn(BoxHandler) -> BoxHandler) -> Self {
+        Self {
+            not_found: self.not_found.map(&f),
+            method_not_allowed: self.method_not_allowed.map(&f),
+            bad_request: self.bad_request.map(&f),
+            internal_error: self.internal_error.map(&f),
+        }
+    }
+}
+
+/// A set of routes and static assets that can be built independently,
+/// then nested or merged into other routers and the app.
+#[derive(Default, Clone)]
+pub struct Router {
+    pub routes: Routes,
+    pub assets_routes: AssetsRoutes,
+    /// Fallback handlers of this router
+    pub fallbacks: Fallbacks,
+    /// Fallback handlers of nested routers
+    ///
+    /// ```not_rust
+    /// nest_prefix : fallbacks
+    /// ```
+    pub nested_fallbacks: BTreeMap<String, Fallbacks>,
+    /// URL builder of named routes
+    pub urls: UrlBuilder,
+    /// Trailing slash policy, nested routers follow the policy of the router
+    /// they nested into
+    pub trailing_slash: TrailingSlash,
+    /// Middleware of all routes in this router, the outermost first
+    pub layers: Vec<BoxMiddleware>,
+    /// Bytes of request body read before the handler is called, see
+    /// [`Request::body_limit`]
+    ///
+    /// Host routers without it follow the app's, nested routers follow the
+    /// router they nested into.
+    pub body_limit: Option<usize>,
+    /// State of routes in this router, routes of nested routers keep their
+    /// own
+    pub state: Option<BoxState>,
+}
+
+impl Router {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry route's handler for any method, include extension methods
+    ///
+    /// The route is dropped with an error logged when the same method and
+    /// path already has an unguarded route, use [`Router::register`] to
+    /// handle the conflict.
+    ///
+    /// ## Arguments
+    ///
+    /// - `method`: the request method, matched case-sensitively
+    /// - `path`: registry route's path
+    /// - `handler`: route handler
+    #[inline]
+    pub fn route<H, T>(mut self, method: Method, path: &str, handler: H) -> Self
+    where
+        H: Handler<T>,
+    {
+        if let Err(err) = self.add_route(Route::new(method, path, handler)) {
+            error!("register route failed {err}");
+        }
+        self
+    }
+
+    /// Registry a route built with [`Route::new`], like a guarded one
+    ///
+    /// Routes of the same method and path are candidates, they are checked
+    /// in registry order. Fail when the same method and path already has an
+    /// unguarded route and this one is unguarded too, or the route's name is
+    /// already registered.
+    #[inline]
+    pub fn register(mut self, route: Route) -> Result<Self> {
+        self.add_route(route)?;
+        Ok(self)
+    }
+
+    /// Serve static assets under `route_path`
+    ///
+    /// ## Arguments
+    ///
+    /// - `route_path`: registry route's path
+    /// - `assets_path`: the static assets path
+    #[inline]
+    pub fn assets(mut self, route_path: &str, assets_path: &Path) -> Self {
+        self.add_assets(route_path, assets_path);
+        self
+    }
+
+    /// Attach middleware to all routes of this router, include the routes
+    /// registered later, the first attached runs first
+    ///
+    /// Middleware of a host router runs around every response of the host,
+    /// like the app's, see [`crate::Rymo::layer`].
+    #[inline]
+    pub fn layer(mut self, middleware: impl Middleware) -> Self {
+        self.layers.push(Arc::new(middleware));
+        self
+    }
+
+    /// Set trailing slash policy, see [`TrailingSlash`]
+    #[inline]
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    /// Set body limit, see [`Router::body_limit`]
+    #[inline]
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = Some(limit);
+        self
+    }
+
+    /// Share `state` with handlers of this router, see
+    /// [`crate::extract::State`]
+    ///
+    /// It's cloned for every request, wrap it in `Arc` when it's not cheap
+    /// to clone.
+    #[inline]
+    pub fn with_state<T>(mut self, state: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.state = Some(Arc::new(state));
+        self
+    }
+
+    /// Mount all routes of `router` under `prefix`
+    ///
+    /// Handlers of the nested router see the request path without `prefix`,
+    /// see [`Request::path`].
+    ///
+    /// ## Arguments
+    ///
+    /// - `prefix`: path prefix like `/api/v1`
+    /// - `router`: the nested router
+    #[inline]
+    pub fn nest(mut self, prefix: &str, router: Router) -> Result<Self> {
+        self.nest_router(prefix, router)?;
+        Ok(self)
+    }
+
+    /// Mount `router` under `prefix` with a part of this router's state,
+    /// see [`Router::nest`]
+    ///
+    /// ```not_rust
+    /// let app = Router::new().with_state(AppState { db, config });
+    /// let app = app.nest_with_state("/users", users, |s: &AppState| s.db.clone())?;
+    /// ```
+    ///
+    /// Fails when this router has no state of type `S`.
+    #[inline]
+    pub fn nest_with_state<S, T>(
+        mut self,
+        prefix: &str,
+        router: Router,
+        project: impl FnOnce(&S) -> T,
+    ) -> Result<Self>
+    where
+        S: 'static,
+        T: Clone + Send + Sync + 'static,
+    {
+        let substate = project_state(self.state.as_ref(), project)?;
+        self.nest_router(prefix, router.with_state(substate))?;
+        Ok(self)
+    }
+
+    /// Merge all routes of `router` into this one
+    #[inline]
+    pub fn merge(mut self, router: Router) -> Result<Self> {
+        self.merge_router(router)?;
+        Ok(self)
+    }
+
+    /// Every registered route and static assets, see [`RouteTable`]
+    #[inline]
+    pub fn route_table(&self) -> RouteTable {
+        RouteTable::from_router(None, self)
+    }
+
+    /// Find fallback handler for `status`, the nearest nested router first
+    #[inline]
+    pub fn fallback_handler(&self, path: &str, status: &Status) -> Option<&BoxHandler> {
+        self.nested_fallbacks
+            .iter()
+            .rev()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|p| p.is_empty() || p.starts_with('/'))
+            })
+            .find_map(|(_, f)| f.get(status))
+            .or_else(|| self.fallbacks.get(status))
+    }
+
+    /// Find route's candidates by request path
+    ///
+    /// Static path matched first, then the pattern with most static segments,
+    /// `:param` is preferred over `*rest` of the same. Patterns still tied
+    /// prefer the earlier static segment, like `/a/b/:y` over `/a/:x/b`,
+    /// then the lexically smaller pattern. Return the path parameters that
+    /// pattern matched.
+    pub fn find(&self, path: &str) -> Option<(&MethodRoutes, Params)> {
+        // `/users/:id` sent literally is matched by the pattern
+        if let Some(handlers) = self.routes.get(path).filter(|_| !is_pattern(path)) {
+            return Some((handlers, vec![]));
+        }
+        self.routes
+            .iter()
+            .filter(|(pattern, _)| is_pattern(pattern))
+            .filter_map(|(pattern, handlers)| {
+                match_path(pattern, path).map(|params| (pattern, handlers, params))
+            })
+            .max_by(|(a, _, _), (b, _, _)| rank(a).cmp(&rank(b)).then_with(|| b.cmp(a)))
+            .map(|(_, handlers, params)| (handlers, params))
+    }
+
+    /// Find static assets that mounted on the longest prefix of request path
+    ///
+    /// Return the registry route's path and the assets path.
+    pub fn find_assets(&self, path: &str) -> Option<(&str, &Path)> {
+        self.assets_routes
+            .iter()
+            .rev()
+            .find(|(key, _)| {
+                let key = key.trim_end_matches('/');
+                path.strip_prefix(key)
+                    .is_some_and(|p| p.is_empty() || p.starts_with('/'))
+            })
+            .map(|(key, path)| (key.as_str(), path.as_path()))
+    }
+
+    /// Replace all routes of the same method and path
+    #[inline]
+    pub(crate) fn replace_route(&mut self, route: Route) -> Result<()> {
+        self.remove_route(&route.method, &route.path);
+        self.add_route(route)
+    }
+
+    /// Remove all routes of the method and path, and their names
+    pub(crate) fn remove_route(&mut self, method: &Method, path: &str) -> bool {
+        let Some(handlers) = self.routes.get_mut(path) else {
+            return false;
+        };
+        let Some(removed) = handlers.remove(method) else {
+            return false;
+        };
+        if handlers.is_empty() {
+            self.routes.remove(path);
+        }
+        removed
+            .iter()
+            .filter_map(|r| r.name.as_ref())
+            .for_each(|name| self.urls.remove(name));
+        true
+    }
+
+    /// Fail without any change when the route and an existing route of the
+    /// same method and path are both unguarded, or the route's name is
+    /// already registered
+    #[inline]
+    pub(crate) fn add_route(&mut self, route: Route) -> Result<()> {
+        let unguarded = self
+            .routes
+            .get(&route.path)
+            .and_then(|handlers| handlers.get(&route.method))
+            .is_some_and(|candidates| candidates.iter().any(|r| r.guards.is_empty()));
+        if route.guards.is_empty() && unguarded {
+            bail!("route conflict {} {}", route.method, route.path);
+        }
+        if let Some(name) = &route.name {
+            self.urls.insert(name, &route.path)?;
+        }
+        self.routes
+            .entry(route.path.clone())
+            .or_default()
+            .entry(route.method.clone())
+            .or_default()
+            .push(route);
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn add_assets(&mut self, route_path: &str, assets_path: &Path) {
+        self.assets_routes
+            .entry(route_path.to_owned())
+            .or_insert(assets_path.to_path_buf());
+    }
+
+    pub(crate) fn nest_router(&mut self, prefix: &str, router: Router) -> Result<()> {
+        if !prefix.starts_with('/') {
+            bail!("nest prefix must start with `/`, got {prefix}");
+        }
+        let prefix = prefix.trim_end_matches('/');
+        if prefix.is_empty() {
+            bail!("nest prefix cannot be `/`, use merge instead");
+        }
+
+        let routes = router
+            .routes
+            .into_iter()
+            .map(|(path, handlers)| {
+                let path = join_path(prefix, &path);
+                let handlers = handlers
+                    .into_iter()
+                    .map(|(method, candidates)| {
+                        let candidates = candidates
+                            .into_iter()
+                            .map(|route| Route {
+                                path: path.clone(),
+                                nest_path: format!("{prefix}{}", route.nest_path),
+                                state: route.state.or_else(|| router.state.clone()),
+                                ..route
+                            })
+                            .collect();
+                        (method, candidates)
+                    })
+                    .collect();
+                (path, handlers)
+            })
+            .collect();
+        let assets_routes = router
+            .assets_routes
+            .into_iter()
+            .map(|(path, assets_path)| (join_path(prefix, &path), assets_path))
+            .collect();
+        let mut nested_fallbacks = router
+            .nested_fallbacks
+            .into_iter()
+            .map(|(path, f)| (join_path(prefix, &path), f))
+            .collect::<BTreeMap<_, _>>();
+        nested_fallbacks.insert(prefix.to_owned(), router.fallbacks);
+        let nested_fallbacks = nested_fallbacks
+            .into_iter()
+            .map(|(path, f)| (path, f.map(|h| nest_handler(prefix, h))))
+            .collect();
+        self.merge_router(Router {
+            routes,
+            assets_routes,
+            nested_fallbacks,
+            layers: router.layers,
+            ..Default::default()
+        })
+    }
+
+    /// Merge routes, fail without any change when the same method and path
+    /// already has unguarded route, the same route name or the same assets
+    /// path already registered
+    ///
+    /// Fallback handlers already registered are kept. Middleware of `router`
+    /// only applies to it's own routes.
+    pub(crate) fn merge_router(&mut self, router: Router) -> Result<()> {
+        let mut conflicts = router
+            .routes
+            .iter()
+            .flat_map(|(path, handlers)| {
+                let exists = self.routes.get(path);
+                handlers
+                    .iter()
+                    .filter(move |(m, candidates)| {
+                        let unguarded = |c: &Vec<Route>| c.iter().any(|r| r.guards.is_empty());
+                        unguarded(candidates)
+                            && exists.and_then(|e| e.get(*m)).is_some_and(unguarded)
+                    })
+                    .map(move |(m, _)| format!("{m} {path}"))
+            })
+            .collect::<Vec<_>>();
+        conflicts.extend(
+            router
+                .routes
+                .values()
+                .flat_map(|handlers| handlers.values().flatten())
+                .filter_map(|r| r.name.as_ref())
+                .filter(|name| self.urls.contains(name))
+                .map(|name| format!("route name {name}")),
+        );
+        conflicts.extend(
+            router
+                .assets_routes
+                .keys()
+                .filter(|path| self.assets_routes.contains_key(*path))
+                .map(|path| format!("assets {path}")),
+        );
+        if !conflicts.is_empty() {
+            bail!("route conflict {}", conflicts.join(", "));
+        }
+
+        let layers = router.layers;
+        let state = router.state;
+        router
+            .routes
+            .into_values()
+            .flat_map(|handlers| handlers.into_values().flatten())
+            .try_for_each(|route| {
+                let middleware = layers.iter().cloned().chain(route.middleware).collect();
+                self.add_route(Route {
+                    middleware,
+                    state: route.state.or_else(|| state.clone()),
+                    ..route
+                })
+            })?;
+        self.assets_routes.extend(router.assets_routes);
+        self.fallbacks = std::mem::take(&mut self.fallbacks).or(router.fallbacks);
+        router.nested_fallbacks.into_iter().for_each(|(path, f)| {
+            let exists = self.nested_fallbacks.remove(&path).unwrap_or_default();
+            self.nested_fallbacks.insert(path, exists.or(f));
+        });
+        Ok(())
+    }
+}
+
+/// Registry fallback handler
+macro_rules! fallback_handler {
+    ($fn_name:ident, $doc:literal) => {
+        impl Router {
+            #[doc = $doc]
+            pub fn $fn_name<H, T>(mut self, handler: H) -> Self
+            where
+                H: Handler<T>,
+            {
+                self.fallbacks.$fn_name = Some(box_handler(handler));
+                self
+            }
+        }
+    };
+}
+fallback_handler!(not_found, "Handler for 404 Not Found");
+fallback_handler!(method_not_allowed, "Handler for 405 Method Not Allowed");
+fallback_handler!(bad_request, "Handler for 400 Bad Request");
+fallback_handler!(internal_error, "Handler for 500 Internal Server Error");
+
+/// Box route handler
+#[inline]
+pub(crate) fn box_handler<H, T>(handler: H) -> BoxHandler
+where
+    H: Handler<T>,
+{
+    let handler = Arc::new(handler);
+    Arc::new(move |req, res| handler.clone().call(req, res))
+}
+
+/// Record nest prefix on request before call the nested handler
+#[inline]
+fn nest_handler(prefix: &str, handler: BoxHandler) -> BoxHandler {
+    let prefix = prefix.to_owned();
+    Arc::new(move |mut req: Request, res| {
+        req.nest_path.push_str(&prefix);
+        handler(req, res)
+    })
+}
+
+/// Match request path with route pattern, return the path parameters
+///
+/// `/users/:id` matches `/users/1`, `/files/*path` matches `/files/a/b.txt`
+fn match_path(pattern: &str, path: &str) -> Option<Params> {
+    let mut params = vec![];
+    let mut segments = path.split('/');
+    for p in pattern.split('/') {
+        if let Some(key) = p.strip_prefix('*') {
+            let rest = segments.collect::<Vec<_>>().join("/");
+            params.push((key.to_owned(), decode(&rest)));
+            return Some(params);
+        }
+        let segment = segments.next()?;
+        match p.strip_prefix(':') {
+            Some(_) if segment.is_empty() => return None,
+            Some(key) => params.push((key.to_owned(), decode(segment))),
+            None if p != segment => return None,
+            None => {}
+        }
+    }
+    segments.next().is_none().then_some(params)
+}
+
+/// Any segment is `:param` or `*rest`
+#[inline]
+fn is_pattern(path: &str) -> bool {
+    path.split('/').any(|s| s.starts_with([':', '*']))
+}
+
+/// Static segments, no catch-all, then kinds of segments in order, the
+/// greater is more specific
+#[inline]
+fn rank(pattern: &str) -> (usize, bool, Vec<u8>) {
+    let kinds = pattern
+        .split('/')
+        .map(|s| match s.as_bytes().first() {
+            Some(b'*') => 0,
+            Some(b':') => 1,
+            _ => 2,
+        })
+        .collect::<Vec<_>>();
+    let static_segments = kinds.iter().filter(|k| **k == 2).count();
+    (static_segments, !kinds.contains(&0), kinds)
+}
+
+/// Percent decode path parameter, keep it as is when it's not utf-8
+#[inline]
+fn decode(value: &str) -> String {
+    percent_decode_str(value)
+        .decode_utf8()
+        .map_or_else(|_| value.to_owned(), |v| v.into_owned())
+}
+
+/// Project `state` of type `S` to a substate
+#[inline]
+pub(crate) fn project_state<S, T>(
+    state: Option<&BoxState>,
+    project: impl FnOnce(&S) -> T,
+) -> Result<T>
+where
+    S: 'static,
+{
+    state
+        .and_then(|s| s.downcast_ref::<S>())
+        .map(project)
+        .ok_or_else(|| anyhow!("no state of type {}", type_name::<S>()))
+}
+
+/// `/api` + `/` is `/api`, `/api` + `/users` is `/api/users`
+#[inline]
+fn join_path(prefix: &str, path: &str) -> String {
+    match path {
+        "" | "/" => prefix.to_owned(),
+        p if p.starts_with('/') => format!("{prefix}{p}"),
+        p => format!("{prefix}/{p}"),
+    }
+}
+
+/// Registry route's handler
+macro_rules! http_handler {
+    ($fn_name:ident, $method:ident) => {
+        impl Router {
+            pub fn $fn_name<H, T>(self, path: &str, handler: H) -> Self
+            where
+                H: Handler<T>,
+            {
+                self.route(Method::$method, path, handler)
+            }
+        }
+    };
+}
+http_handler!(get, Get);
+http_handler!(head, Head);
+http_handler!(post, Post);
+http_handler!(put, Put);
+http_handler!(delete, Delete);
+http_handler!(connect, Connect);
+http_handler!(options, Options);
+http_handler!(trace, Trace);
+http_handler!(patch, Patch);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Params {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn match_patterns() {
+        assert_eq!(
+            match_path("/users/:id", "/users/1"),
+            Some(params(&[("id", "1")]))
+        );
+        assert_eq!(
+            match_path("/users/:id/posts/:post", "/users/1/posts/2"),
+            Some(params(&[("id", "1"), ("post", "2")]))
+        );
+        assert_eq!(
+            match_path("/files/*path", "/files/a/b.txt"),
+            Some(params(&[("path", "a/b.txt")]))
+        );
+        assert_eq!(
+            match_path("/users/:name", "/users/J%C3%BCrgen%20D"),
+            Some(params(&[("name", "J\u{fc}rgen D")]))
+        );
+        // not UTF-8 after decoding, kept as is
+        assert_eq!(
+            match_path("/users/:name", "/users/%FF"),
+            Some(params(&[("name", "%FF")]))
+        );
+    }
+
+    #[test]
+    fn reject_mismatched_paths() {
+        for (pattern, path) in [
+            ("/users/:id", "/users"),
+            ("/users/:id", "/users/"),
+            ("/users/:id", "/users/1/posts"),
+            ("/users/:id", "/posts/1"),
+            ("/users/:id/posts", "/users/1/comments"),
+            ("/files/*path", "/static/a"),
+        ] {
+            assert_eq!(match_path(pattern, path), None, "{pattern} {path}");
+        }
+    }
+
+    #[test]
+    fn find_most_specific_route() {
+        let router = Router::new()
+            .get("/users/:id", || async { "" })
+            .get("/users/me", || async { "" })
+            .get("/users/:id/posts", || async { "" })
+            .get("/users/*rest", || async { "" });
+        let params_of = |path| router.find(path).map(|(_, params)| params);
+        assert_eq!(params_of("/users/me"), Some(vec![]));
+        assert_eq!(params_of("/users/1"), Some(params(&[("id", "1")])));
+        assert_eq!(params_of("/users/1/posts"), Some(params(&[("id", "1")])));
+        assert_eq!(
+            params_of("/users/1/likes"),
+            Some(params(&[("rest", "1/likes")]))
+        );
+        assert_eq!(params_of("/posts"), None);
+    }
+
+    #[test]
+    fn find_pattern_sent_literally() {
+        let router = Router::new().get("/users/:id", || async { "" });
+        let params_of = |path| router.find(path).map(|(_, params)| params);
+        assert_eq!(params_of("/users/:id"), Some(params(&[("id", ":id")])));
+        assert_eq!(params_of("/users/1"), Some(params(&[("id", "1")])));
+    }
+
+    #[test]
+    fn break_ties_deterministically() {
+        for _ in 0..50 {
+            let router = Router::new()
+                .get("/a/:x/b", || async { "" })
+                .get("/a/b/:y", || async { "" })
+                .get("/users/:name", || async { "" })
+                .get("/users/:id", || async { "" });
+            let params_of = |path| router.find(path).map(|(_, params)| params);
+            assert_eq!(params_of("/a/b/b"), Some(params(&[("y", "b")])));
+            assert_eq!(params_of("/a/c/b"), Some(params(&[("x", "c")])));
+            assert_eq!(params_of("/users/1"), Some(params(&[("id", "1")])));
+        }
+    }
+
+    #[test]
+    fn reject_duplicate_unguarded_route() {
+        let router = Router::new().get("/a", || async { "first" });
+        let err = router
+            .clone()
+            .register(Route::new(Method::Get, "/a", || async { "second" }).name("a"))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "route conflict GET /a");
+
+        // the route and it's name are not registered
+        let router = router.get("/a", || async { "second" });
+        assert_eq!(router.routes["/a"][&Method::Get].len(), 1);
+        assert!(!router.urls.contains("a"));
+
+        let guarded = Route::new(Method::Get, "/a", || async { "guarded" })
+            .guard(Guard::new(Status::NotFound, |_| true));
+        assert!(router.register(guarded).is_ok());
+    }
+
+    /// Path, nest path and `id` parameter seen by the handler
+    async fn seen(req: Request) -> String {
+        format!(
+            "{} {} {}",
+            req.path(),
+            req.nest_path,
+            req.param("id").unwrap_or("-")
+        )
+    }
+
+    async fn get(router: &Router, path: &str) -> Response {
+        let head = format!("GET {path} HTTP/1.1\r\nHost: x\r\n\r\n");
+        let req = Request::parse_from_bytes(head.into()).unwrap();
+        let hosts = Arc::new(crate::host::VirtualHosts {
+            default: router.clone(),
+            ..Default::default()
+        });
+        crate::server::dispatch(hosts, req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn nest_strips_prefix() {
+        let users = Router::new().get("/", seen).get("/:id", seen);
+        let v1 = Router::new().nest("/users", users).unwrap();
+        let router = Router::new().nest("/api/v1/", v1).unwrap();
+        assert!(router.routes.contains_key("/api/v1/users/:id"));
+
+        let res = get(&router, "/api/v1/users/7").await;
+        assert_eq!(res.body, "/7 /api/v1/users 7");
+        let res = get(&router, "/api/v1/users").await;
+        assert_eq!(res.body, "/ /api/v1/users -");
+    }
+
+    #[tokio::test]
+    async fn nested_fallbacks_apply_under_prefix() {
+        let api = Router::new()
+            .get("/users", seen)
+            .not_found(|req: Request| async move { format!("api {}", req.path()) });
+        let router = Router::new()
+            .not_found(|| async { "app" })
+            .nest("/api", api)
+            .unwrap();
+
+        let res = get(&router, "/api/missing").await;
+        assert_eq!(res.status, Status::NotFound);
+        assert_eq!(res.body, "api /missing");
+        let res = get(&router, "/missing").await;
+        assert_eq!(res.status, Status::NotFound);
+        assert_eq!(res.body, "app");
+    }
+
+    #[test]
+    fn reject_invalid_nest_prefix() {
+        for prefix in ["api", "/", ""] {
+            assert!(
+                Router::new().nest(prefix, Router::new()).is_err(),
+                "{prefix}"
+            );
+        }
+    }
+
+    #[test]
+    fn reject_merge_conflicts() {
+        let named = |path: &str, name: &str| {
+            Router::new()
+                .register(Route::new(Method::Get, path, seen).name(name))
+                .unwrap()
+        };
+        let router = named("/a", "a").assets("/static", Path::new("public"));
+
+        let conflicts = [
+            (Router::new().get("/a", seen), "route conflict GET /a"),
+            (named("/b", "a"), "route conflict route name a"),
+            (
+                Router::new().assets("/static", Path::new("other")),
+                "route conflict assets /static",
+            ),
+        ];
+        for (other, message) in conflicts {
+            let err = router.clone().merge(other).err().unwrap();
+            assert_eq!(err.to_string(), message);
+        }
+
+        // guarded routes of the same path are candidates
+        let guarded = Router::new()
+            .register(
+                Route::new(Method::Get, "/a", seen).guard(Guard::new(Status::NotFound, |_| true)),
+            )
+            .unwrap();
+        assert!(router.clone().merge(guarded).is_ok());
+        // nested routes conflict by their full path
+        let nested = Router::new().get("/x", seen);
+        let router = router.nest("/n", nested.clone()).unwrap();
+        assert!(router.clone().nest("/n", nested).is_err());
+    }
+
+    #[test]
+    fn failed_merge_changes_nothing() {
+        let mut router = Router::new()
+            .get("/a", seen)
+            .register(Route::new(Method::Get, "/named", seen).name("named"))
+            .unwrap();
+        let other = Router::new()
+            .register(Route::new(Method::Get, "/b", seen).name("b"))
+            .unwrap()
+            .assets("/static", Path::new("public"))
+            .not_found(|| async { "other" })
+            .get("/a", seen);
+
+        assert!(router.merge_router(other).is_err());
+        assert_eq!(router.routes.len(), 2);
+        assert!(!router.routes.contains_key("/b"));
+        assert!(!router.urls.contains("b"));
+        assert!(router.urls.contains("named"));
+        assert!(router.assets_routes.is_empty());
+        assert!(router.fallbacks.not_found.is_none());
+    }
+
+    #[derive(Clone)]
+    struct AppState {
+        db: u32,
+    }
+
+    #[tokio::test]
+    async fn nested_router_sees_projected_state() {
+        use crate::extract::State;
+
+        let users = Router::new()
+            .get("/db", |State(db): State<u32>| async move { db.to_string() })
+            .get("/app", |State(app): State<AppState>| async move {
+                app.db.to_string()
+            });
+        let router = Router::new()
+            .get("/", |State(app): State<AppState>| async move {
+                app.db.to_string()
+            })
+            .with_state(AppState { db: 7 })
+            .nest_with_state("/users", users, |s: &AppState| s.db + 1)
+            .unwrap();
+
+        assert_eq!(get(&router, "/").await.body, "7");
+        assert_eq!(get(&router, "/users/db").await.body, "8");
+        // the app state is not visible to the nested router
+        let res = get(&router, "/users/app").await;
+        assert_eq!(res.status, Status::InternalServer);
+    }
+
+    #[test]
+    fn nest_with_state_requires_state_type() {
+        let project = |s: &AppState| s.db;
+        let err = Router::new()
+            .nest_with_state("/users", Router::new(), project)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("AppState"), "{err}");
+        let err = Router::new()
+            .with_state(1u8)
+            .nest_with_state("/users", Router::new(), project)
+            .err();
+        assert!(err.is_some());
+    }
+}
@@ -1,84 +1,96 @@
-use crate::{
-    error::{Error, Result},
-    http::mime::{read_mime, HTML_UTF_8},
-    request::{drop_body, read_body, read_headers, Request},
-    response::{Response, Status},
-    utils::find_directory,
-};
-use futures::Future;
-use log::{error, info};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::BTreeSet,
     ffi::OsStr,
     path::{Path, PathBuf},
+    pin::pin,
     sync::Arc,
 };
+
+use bytes::Bytes;
+use futures::{
+    future::{select, Either},
+    stream, StreamExt,
+};
+use log::{debug, error, info};
 use tokio::{
     fs,
     io::{AsyncRead, AsyncWriteExt},
     net::{TcpListener, TcpStream},
-    sync::RwLock,
+    sync::mpsc,
 };
 
-pub type Routes<F> = Arc<RwLock<HashMap<&'static str, HashMap<&'static str, F>>>>;
-pub type AssetsRoutes = Arc<RwLock<BTreeMap<&'static str, PathBuf>>>;
+use crate::{
+    error::{Error, Result},
+    handle::RouterHandle,
+    handler::Handler,
+    host::VirtualHosts,
+    http::{method::Method, mime::read_mime},
+    middleware::{BoxMiddleware, Middleware, Next},
+    request::{read_headers, BodyReader, Request, DEFAULT_BODY_LIMIT},
+    response::{write_response, Response, Status},
+    router::{
+        box_handler, project_state, BoxHandler, BoxState, MethodRoutes, Params, Route, Router,
+        TrailingSlash,
+    },
+    table::RouteTable,
+};
 
-pub struct Rymo<'a, F, Fut>
-where
-    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-    Fut: Future<Output = anyhow::Result<Response>> + Send,
-{
+/// Unread request body is drained up to this before the connection is
+/// closed, so the client sees the response instead of a reset
+const MAX_DRAIN: usize = 1024 * 1024;
+
+pub struct Rymo<'a> {
     /// Current listen port
     pub port: &'a str,
-    /// Registries routes
-    ///
-    /// ```not_rust
-    /// route_path : {
-    ///     http_method: route_handler
-    /// }
-    /// ```
-    pub routes: Routes<F>,
-    pub assets_routes: AssetsRoutes,
+    /// Registries routes and static assets of every host
+    pub hosts: RouterHandle,
 }
 
-impl<'a, F, Fut> Rymo<'a, F, Fut>
-where
-    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-    Fut: Future<Output = anyhow::Result<Response>> + Send + 'static,
-{
+impl<'a> Rymo<'a> {
     #[inline]
     pub fn new(port: &'a str) -> Self {
         Self {
             port,
-            routes: Arc::new(RwLock::new(HashMap::new())),
-            assets_routes: Arc::new(RwLock::new(BTreeMap::new())),
+            hosts: RouterHandle::default(),
         }
     }
 
+    /// Share `state` with all handlers, see [`crate::extract::State`]
+    ///
+    /// It's cloned for every request, wrap it in `Arc` when it's not cheap
+    /// to clone. Routers with their own state keep it.
+    #[inline]
+    pub fn with_state<T>(self, state: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let state: BoxState = Arc::new(state);
+        self.update(|hosts| hosts.default.state = Some(state));
+        self
+    }
+
     /// Start server
     #[inline]
     pub async fn serve(&self) -> Result<()> {
         let listener = TcpListener::bind(format!("0.0.0.0:{}", self.port)).await?;
+        debug!("serving routes\n{}", self.route_table().await);
 
         loop {
             let (socket, addr) = listener.accept().await?;
             info!("accept connection from {}", addr);
-            let routes = self.routes.clone();
-            let assets_routes = self.assets_routes.clone();
+            // requests of this connection see the routes at the moment
+            let hosts = self.hosts.snapshot();
             let task = async move {
                 let mut socket = socket;
-                match process(&mut socket, routes, assets_routes).await {
+                match process(&mut socket, hosts).await {
                     Ok(_) => {}
                     Err(err) => {
-                        let response = match &err {
-                            Error::BadRequest(_) => {
-                                format!("HTTP/1.1 {}\r\n\r\n", Status::BadRequest)
-                            }
-                            Error::InternalServerError(_) => {
-                                format!("HTTP/1.1 {}\r\n\r\n", Status::InternalServer)
-                            }
-                        };
-                        let _ = socket.write_all(response.as_bytes()).await;
+                        let response: Vec<u8> = Response {
+                            status: err.status(),
+                            ..Default::default()
+                        }
+                        .into();
+                        let _ = socket.write_all(&response).await;
                         let _ = socket.flush().await;
                         error!("handle route failed {}", err);
                     }
@@ -88,6 +100,57 @@ where
         }
     }
 
+    /// Every registered route and static assets of all hosts
+    #[inline]
+    pub async fn route_table(&self) -> RouteTable {
+        RouteTable::from_hosts(&self.hosts.snapshot())
+    }
+
+    /// Apply infallible change to routes
+    #[inline]
+    fn update(&self, f: impl FnOnce(&mut VirtualHosts)) {
+        // only fails when a writer panicked
+        if let Err(err) = self.hosts.update(|hosts| {
+            f(hosts);
+            Ok(())
+        }) {
+            error!("update routes failed {err}");
+        }
+    }
+
+    /// Handle to change routes while serving, see [`RouterHandle`]
+    #[inline]
+    pub fn handle(&self) -> RouterHandle {
+        self.hosts.clone()
+    }
+
+    /// Registry route's handler for any method, include extension methods
+    ///
+    /// A conflicting route is dropped with an error logged, see
+    /// [`Router::route`].
+    ///
+    /// ## Arguments
+    ///
+    /// - `method`: the request method, matched case-sensitively
+    /// - `path`: registry route's path
+    /// - `handler`: route handler
+    #[inline]
+    pub async fn route<H, T>(&self, method: Method, path: &str, handler: H)
+    where
+        H: Handler<T>,
+    {
+        let route = Route::new(method, path, handler);
+        if let Err(err) = self.hosts.update(|hosts| hosts.default.add_route(route)) {
+            error!("register route failed {err}");
+        }
+    }
+
+    /// Registry a route built with [`Route::new`], see [`Router::register`]
+    #[inline]
+    pub async fn register(&self, route: Route) -> anyhow::Result<()> {
+        self.hosts.update(|hosts| hosts.default.add_route(route))
+    }
+
     /// Read target directory and try to find `index.html`
     ///
     /// ## Arguments
@@ -95,11 +158,81 @@ where
     /// - `route_path`: registry route's path
     /// - `assets_path`: the static assets path
     #[inline]
-    pub async fn assets(&self, route_path: &'static str, assets_path: &Path, _handler: F) {
-        let mut routes = self.assets_routes.write().await;
-        routes
-            .entry(route_path)
-            .or_insert(assets_path.to_path_buf());
+    pub async fn assets<H, T>(&self, route_path: &str, assets_path: &Path, _handler: H)
+    where
+        H: Handler<T>,
+    {
+        self.update(|hosts| hosts.default.add_assets(route_path, assets_path));
+    }
+
+    /// Mount all routes of `router` under `prefix`, see [`Router::nest`]
+    #[inline]
+    pub async fn nest(&self, prefix: &str, router: Router) -> anyhow::Result<()> {
+        self.hosts
+            .update(|hosts| hosts.default.nest_router(prefix, router))
+    }
+
+    /// Mount `router` under `prefix` with a part of the app state, see
+    /// [`Router::nest_with_state`]
+    #[inline]
+    pub async fn nest_with_state<S, T>(
+        &self,
+        prefix: &str,
+        router: Router,
+        project: impl FnOnce(&S) -> T,
+    ) -> anyhow::Result<()>
+    where
+        S: 'static,
+        T: Clone + Send + Sync + 'static,
+    {
+        self.hosts.update(|hosts| {
+            let substate = project_state(hosts.default.state.as_ref(), project)?;
+            hosts
+                .default
+                .nest_router(prefix, router.with_state(substate))
+        })
+    }
+
+    /// Merge all routes of `router` into the app, see [`Router::merge`]
+    #[inline]
+    pub async fn merge(&self, router: Router) -> anyhow::Result<()> {
+        self.hosts
+            .update(|hosts| hosts.default.merge_router(router))
+    }
+
+    /// Attach middleware to every host, see [`Middleware`]
+    ///
+    /// It runs around every response, include static assets, fallbacks and
+    /// automatic `OPTIONS`. Errors returned through it are answered by the
+    /// internal error fallback outside of it.
+    #[inline]
+    pub async fn layer(&self, middleware: impl Middleware) {
+        let middleware: BoxMiddleware = Arc::new(middleware);
+        self.update(|hosts| hosts.default.layers.push(middleware));
+    }
+
+    /// Trailing slash policy of the app, see [`TrailingSlash`]
+    #[inline]
+    pub async fn trailing_slash(&self, policy: TrailingSlash) {
+        self.update(|hosts| hosts.default.trailing_slash = policy);
+    }
+
+    /// Bytes of request body read before the handler is called, larger
+    /// bodies are answered with 413, see [`Router::body_limit`]
+    ///
+    /// Defaults to [`DEFAULT_BODY_LIMIT`], host routers without their own
+    /// limit follow it.
+    #[inline]
+    pub async fn body_limit(&self, limit: usize) {
+        self.update(|hosts| hosts.default.body_limit = Some(limit));
+    }
+
+    /// Serve `router` for requests to host `pattern`, see [`VirtualHosts::add`]
+    ///
+    /// Routes registered on the app itself serve the default host.
+    #[inline]
+    pub async fn host(&self, pattern: &str, router: Router) -> anyhow::Result<()> {
+        self.hosts.update(|hosts| hosts.add(pattern, router))
     }
 }
 
@@ -108,182 +241,682 @@ pub async fn static_handler(_req: Request, res: Response) -> anyhow::Result<Resp
     Ok(res)
 }
 
-/// Static assets handler
-async fn assets_handler(
-    req: Request,
-    mut res: Response,
-    assets_key: Option<&str>,
-    assets_path: &Path,
-    is_file: bool,
-) -> Result<Response> {
-    let mut path = assets_path.to_path_buf();
-    // use request path as parent path
-    let parent = &req.path.to_str();
-    // find static assets child directory
-    let directory = match assets_key {
-        Some(key) if parent.is_some() => find_directory(key, parent.unwrap()),
-        _ => None,
+/// Static file that request path resolved to
+#[derive(Debug, PartialEq)]
+enum Asset {
+    File(PathBuf),
+    /// Redirect to the canonical path
+    Redirect(String),
+    NotFound,
+}
+
+/// Resolve request path to file under assets path
+///
+/// Directory is served by it's `index.html`, the canonical path of directory
+/// ends with `/` and file doesn't. Other paths are handled by `policy`.
+///
+/// ## Arguments
+///
+/// - `policy`: trailing slash policy of the router
+/// - `key`: registry route's path of the assets
+/// - `assets_path`: the static assets path
+/// - `path`: request path
+async fn resolve_asset(policy: TrailingSlash, key: &str, assets_path: &Path, path: &str) -> Asset {
+    let rest = path
+        .strip_prefix(key.trim_end_matches('/'))
+        .unwrap_or("")
+        .trim_matches('/');
+    // never serve files outside assets path
+    if rest.split('/').any(|s| s == "..") {
+        return Asset::NotFound;
+    }
+    let file = assets_path.join(rest);
+    let Ok(meta) = fs::metadata(&file).await else {
+        return Asset::NotFound;
     };
-    if let Some(d) = directory {
-        path.push(d);
-    }
-
-    let mime = if is_file {
-        let ext = req
-            .path
-            .extension()
-            .unwrap_or(OsStr::new(""))
-            .to_str()
-            .unwrap_or("");
-        read_mime(ext)
-    } else {
-        path.push("index.html");
-        HTML_UTF_8
+
+    let is_dir = meta.is_dir();
+    let canonical = match (is_dir, path.ends_with('/')) {
+        (true, true) | (false, false) => None,
+        _ => toggle_slash(path),
     };
-    let file = fs::read(path).await?;
-    res.headers
-        .insert("Content-Type".to_owned(), mime.to_owned());
+    match (canonical, policy) {
+        (Some(location), TrailingSlash::Redirect | TrailingSlash::PermanentRedirect) => {
+            Asset::Redirect(location)
+        }
+        (Some(_), TrailingSlash::Strict) => Asset::NotFound,
+        // directory index is served without the slash too
+        (Some(_), TrailingSlash::Legacy) if !is_dir => Asset::NotFound,
+        _ if is_dir => {
+            let index = file.join("index.html");
+            if fs::metadata(&index).await.is_ok_and(|m| m.is_file()) {
+                Asset::File(index)
+            } else {
+                Asset::NotFound
+            }
+        }
+        _ => Asset::File(file),
+    }
+}
+
+/// Static assets handler
+async fn assets_handler(mut res: Response, file: &Path) -> Result<Response> {
+    let ext = file
+        .extension()
+        .unwrap_or(OsStr::new(""))
+        .to_str()
+        .unwrap_or("");
+    let mime = read_mime(ext);
+    let file = fs::read(file).await?;
+    res.headers.insert("Content-Type", mime)?;
     res.body = file.into();
     Ok(res)
 }
 
+/// Redirect to `location` with the query of request
+#[inline]
+fn redirect(policy: TrailingSlash, req: &Request, location: &str) -> Response {
+    let location = match req.uri.query() {
+        Some(query) => format!("{location}?{query}"),
+        None => location.to_owned(),
+    };
+    let mut res = Response {
+        status: policy.redirect_status().unwrap_or(Status::MovedPermanently),
+        ..Default::default()
+    };
+    // request target has no control characters
+    let _ = res.headers.insert("Location", &location);
+    res
+}
+
+/// `/docs/` is `/docs`, `/docs` is `/docs/`
+#[inline]
+fn toggle_slash(path: &str) -> Option<String> {
+    match path {
+        "/" | "*" => None,
+        p if p.ends_with('/') => Some(p.trim_end_matches('/').to_owned()).filter(|p| !p.is_empty()),
+        p => Some(format!("{p}/")),
+    }
+}
+
+/// Registry app's fallback handler
+macro_rules! fallback_handler {
+    ($fn_name:ident, $doc:literal) => {
+        impl<'a> Rymo<'a> {
+            #[doc = $doc]
+            pub async fn $fn_name<H, T>(&self, handler: H)
+            where
+                H: Handler<T>,
+            {
+                let handler = Some(box_handler(handler));
+                self.update(|hosts| hosts.default.fallbacks.$fn_name = handler);
+            }
+        }
+    };
+}
+fallback_handler!(not_found, "Handler for 404 Not Found");
+fallback_handler!(method_not_allowed, "Handler for 405 Method Not Allowed");
+fallback_handler!(bad_request, "Handler for 400 Bad Request");
+fallback_handler!(internal_error, "Handler for 500 Internal Server Error");
+
 /// Registry route's handler
 macro_rules! http_handler {
-    ($fn_name:ident) => {
-        impl<'a, F, Fut> Rymo<'a, F, Fut>
-        where
-            F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-            Fut: Future<Output = anyhow::Result<Response>> + Send,
-        {
-            pub async fn $fn_name(&self, path: &'static str, handler: F) {
-                let mut routes = self.routes.write().await;
-                let path_handler = routes.entry(path).or_default();
-                // route
-                // let route = route::Route::new(path, Some(handler), false, None);
-                path_handler.entry(stringify!($fn_name)).or_insert(handler);
+    ($fn_name:ident, $method:ident) => {
+        impl<'a> Rymo<'a> {
+            pub async fn $fn_name<H, T>(&self, path: &str, handler: H)
+            where
+                H: Handler<T>,
+            {
+                self.route(Method::$method, path, handler).await;
             }
         }
     };
 }
-http_handler!(get);
-http_handler!(head);
-http_handler!(post);
-http_handler!(put);
-http_handler!(delete);
-http_handler!(connect);
-http_handler!(options);
-http_handler!(trace);
-http_handler!(patch);
+http_handler!(get, Get);
+http_handler!(head, Head);
+http_handler!(post, Post);
+http_handler!(put, Put);
+http_handler!(delete, Delete);
+http_handler!(connect, Connect);
+http_handler!(options, Options);
+http_handler!(trace, Trace);
+http_handler!(patch, Patch);
 
 #[inline]
-pub async fn process<F, Fut>(
-    socket: &mut TcpStream,
-    routes: Routes<F>,
-    assets_routes: AssetsRoutes,
-) -> Result<()>
-where
-    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-    Fut: Future<Output = anyhow::Result<Response>> + Send,
-{
+pub async fn process(socket: &mut TcpStream, hosts: Arc<VirtualHosts>) -> Result<()> {
+    let peer_addr = socket.peer_addr().ok();
+    let local_addr = socket.local_addr().ok();
     let (reader, mut writer) = socket.split();
 
     // build client request
-    let (headers, reader) = read_headers(reader)
-        .await
-        .map_err(|e| Error::BadRequest(format!("read headers failed {}", e)))?;
-    let req = Request::parse_from_bytes(headers.clone())
-        .map_err(|e| Error::BadRequest(format!("parse headers from bytes failed {}", e)))?;
-
-    // Registries routes
-    let routes = routes.read().await;
-    let req_str = req.path.to_string_lossy();
-    // the request path is file path or not
-    let is_file = req_str.contains('.') && !req_str.ends_with('/');
-    // if it's file, use it's parent path
-    // GET /index.html use /
-    let req_path = if is_file {
-        req.path
-            .parent()
-            .map(|p| p.to_path_buf())
-            .unwrap_or(PathBuf::from(&req.path))
-    } else {
-        req.path.to_path_buf()
+    let (req, body, tx) = match read_request(reader).await {
+        Err(err) => {
+            error!("{err}");
+            let res = Response {
+                status: Status::BadRequest,
+                ..Default::default()
+            };
+            // the request is unreadable, fallback receive an empty one
+            let req = Request {
+                peer_addr,
+                local_addr,
+                ..Default::default()
+            };
+            let response = hosts.fallback(&hosts.default, req, res).await;
+            if let Err(err) = write_response(&mut writer, response).await {
+                error!("write response failed {err}");
+            }
+            return Ok(());
+        }
+        Ok((mut req, reader)) => {
+            req.peer_addr = peer_addr;
+            req.local_addr = local_addr;
+            let body = BodyReader::new(reader, &req);
+            // pulled by the handler, or by routing when it reads the body
+            let (tx, rx) = mpsc::channel(1);
+            let stream = stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|chunk| (chunk, rx))
+            });
+            req.set_body_stream(stream.boxed());
+            (req, body, tx)
+        }
     };
-
-    // static assets routes
-    let assets_routes = assets_routes.read().await;
-    // loop for assets routes
-    let req_path_str = req_path.to_string_lossy();
-    let (x, mut y) = (0, 1);
-    let (key, assets_path) = loop {
-        if y > req_path_str.len() {
-            break (None, None);
+    let respond = async {
+        let response = dispatch(hosts.clone(), req).await?;
+        // a failed stream leaves the response incomplete, the connection is
+        // closed anyway
+        if let Err(err) = write_response(&mut writer, response).await {
+            error!("write response failed {err}");
         }
-        let key = &req_path_str[x..y];
-        let assets_path = assets_routes.get(key);
-        match assets_path {
-            Some(path) => {
-                break (key.into(), Some(path));
+        Ok(())
+    };
+    let (result, mut body) = futures::join!(respond, pump_body(body, tx));
+    if !body.drain(MAX_DRAIN).await {
+        debug!("close connection with unread request body");
+    }
+    result
+}
+
+/// Send request body to it's stream until the body ends or the stream is
+/// dropped
+async fn pump_body<R>(
+    mut body: BodyReader<R>,
+    tx: mpsc::Sender<anyhow::Result<Bytes>>,
+) -> BodyReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        let chunk = match select(pin!(body.next_chunk()), pin!(tx.closed())).await {
+            Either::Left((chunk, _)) => chunk,
+            Either::Right(_) => break,
+        };
+        match chunk {
+            Ok(Some(chunk)) => {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    break;
+                }
             }
-            None => {
-                y += 1;
+            Ok(None) => break,
+            Err(err) => {
+                let _ = tx.send(Err(err)).await;
+                break;
             }
         }
+    }
+    body
+}
+
+/// Route a parsed request, it's body is read by the handler or drained by
+/// the caller
+///
+/// Middleware of the app and the host router runs around every response,
+/// include static assets, redirects, fallbacks and automatic `OPTIONS`.
+pub(crate) async fn dispatch(hosts: Arc<VirtualHosts>, req: Request) -> Result<Response> {
+    // select virtual host then registries routes
+    let router = hosts.select(req.host());
+    let mut req = req;
+    req.state = router.state.clone().or_else(|| hosts.default.state.clone());
+    req.body_limit = router
+        .body_limit
+        .or(hosts.default.body_limit)
+        .unwrap_or(DEFAULT_BODY_LIMIT);
+
+    // HEAD is answered like GET without body
+    let is_head = req.method == Method::Head;
+    // keep request for internal error fallback, the body is not read yet
+    let origin_req = hosts
+        .fallback_handler(router, req.uri.path(), &Status::InternalServer)
+        .map(|_| req.clone());
+    // streaming routes read the body themselves, middleware of other
+    // requests sees the whole body
+    let body_error = match streams_body(router, &req) {
+        true => None,
+        false => req.read_body().await.err().map(|err| {
+            error!("read body failed {err}");
+            match err.downcast_ref::<Error>() {
+                Some(err) => err.status(),
+                None => Status::BadRequest,
+            }
+        }),
     };
+    // app, then host router's middleware
+    let layers = if std::ptr::eq(router, &hosts.default) {
+        router.layers.clone()
+    } else {
+        hosts
+            .default
+            .layers
+            .iter()
+            .chain(&router.layers)
+            .cloned()
+            .collect()
+    };
+    let routes = hosts.clone();
+    let handler: BoxHandler = Arc::new(move |req, res| {
+        let hosts = routes.clone();
+        let body_error = body_error.clone();
+        Box::pin(async move { route(&hosts, req, res, body_error).await })
+    });
+    let response = Next::new(layers, handler)
+        .run(req, Response::default())
+        .await;
+    let mut response = match (response, origin_req) {
+        (Ok(res), _) => res,
+        (Err(err), Some(req)) => {
+            error!("handle route failed {err}");
+            let res = Response {
+                status: Status::InternalServer,
+                ..Default::default()
+            };
+            let router = hosts.select(req.host());
+            hosts.fallback(router, req, res).await
+        }
+        (Err(err), None) => return Err(err.into()),
+    };
+    if is_head {
+        response.strip_body();
+    }
+    Ok(response)
+}
 
-    let response = match assets_path {
+/// The matched route streams it's body, static assets never do
+#[inline]
+fn streams_body(router: &Router, req: &Request) -> bool {
+    router.find_assets(req.uri.path()).is_none()
+        && find_route(router, req.uri.path())
+            .and_then(|(handler, _, _)| find_method(handler, &req.method))
+            .is_some_and(|candidates| candidates.iter().any(|r| r.stream_body))
+}
+
+/// Find routes of request path, try `/docs` for `/docs/` and `/docs/` for
+/// `/docs` unless the policy matches only the canonical path of routes
+///
+/// Return the path parameters and the canonical path when it was toggled.
+#[inline]
+fn find_route<'a>(
+    router: &'a Router,
+    path: &str,
+) -> Option<(&'a MethodRoutes, Params, Option<String>)> {
+    if let Some((handler, params)) = router.find(path) {
+        return Some((handler, params, None));
+    }
+    if matches!(
+        router.trailing_slash,
+        TrailingSlash::Strict | TrailingSlash::Legacy
+    ) {
+        return None;
+    }
+    let path = toggle_slash(path)?;
+    router
+        .find(&path)
+        .map(|(handler, params)| (handler, params, Some(path)))
+}
+
+/// Candidates of the request method, HEAD falls back to GET
+#[inline]
+fn find_method<'a>(handler: &'a MethodRoutes, method: &Method) -> Option<&'a Vec<Route>> {
+    handler.get(method).or_else(|| match method {
+        Method::Head => handler.get(&Method::Get),
+        _ => None,
+    })
+}
+
+/// Serve static assets, regular routes or fallbacks, inside the middleware
+/// of the app and the host router
+async fn route(
+    hosts: &VirtualHosts,
+    mut req: Request,
+    mut res: Response,
+    body_error: Option<Status>,
+) -> anyhow::Result<Response> {
+    let router = hosts.select(req.host());
+    let routes = &router.routes;
+    let policy = router.trailing_slash;
+    if let Some((key, assets_path)) = router.find_assets(req.uri.path()) {
         // handle static serve
-        Some(path) => {
-            let res = Response::default();
-            assets_handler(req, res, key, path, is_file).await?.into()
+        return match resolve_asset(policy, key, assets_path, req.uri.path()).await {
+            Asset::File(file) => assets_handler(res, &file)
+                .await
+                .map_err(anyhow::Error::from),
+            Asset::Redirect(location) => Ok(redirect(policy, &req, &location)),
+            Asset::NotFound => {
+                res.status = Status::NotFound;
+                Ok(hosts.fallback(router, req, res).await)
+            }
+        };
+    }
+    // server-wide OPTIONS * without registered route
+    if req.method == Method::Options && req.path() == "*" && !routes.contains_key("*") {
+        let allow = allow_methods(routes.values().flat_map(|r| r.keys()));
+        res.headers.insert("Allow", &allow)?;
+        return Ok(res);
+    }
+    if let Some(status) = body_error {
+        res.status = status;
+        return Ok(hosts.fallback(router, req, res).await);
+    }
+
+    // handle regular routes
+    req.urls = router.urls.clone();
+    let route_handler = match find_route(router, req.uri.path()) {
+        Some((_, _, Some(location))) if policy.redirect_status().is_some() => {
+            return Ok(redirect(policy, &req, &location));
         }
-        // handle regular routes
-        None => {
-            let route_handler = routes.get(req_path_str.as_ref());
-            handle_route(route_handler, req, reader).await?
+        Some((handler, params, _)) => {
+            req.params = params;
+            Some(handler)
         }
+        None => None,
     };
-
-    // let response = handle_route(route_handler, req, reader).await?;
-    writer.write_all(&response).await?;
-    writer.flush().await?;
-    Ok(())
+    handle_route(hosts, router, route_handler, req, res).await
 }
 
-async fn handle_route<F, Fut, R>(
-    route_handler: Option<&HashMap<&str, F>>,
-    mut req: Request,
-    reader: R,
-) -> anyhow::Result<Vec<u8>>
+/// Read and parse request headers
+#[inline]
+async fn read_request<R>(reader: R) -> Result<(Request, R)>
 where
-    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-    Fut: Future<Output = anyhow::Result<Response>> + Send,
     R: AsyncRead + Unpin,
 {
-    // parse body
-    let content_len = req.headers.get("content-length");
+    let (headers, reader) = read_headers(reader)
+        .await
+        .map_err(|e| Error::BadRequest(format!("read headers failed {}", e)))?;
+    let req = Request::parse_from_bytes(headers)
+        .map_err(|e| Error::BadRequest(format!("parse headers from bytes failed {}", e)))?;
+    Ok((req, reader))
+}
+
+async fn handle_route(
+    hosts: &VirtualHosts,
+    router: &Router,
+    route_handler: Option<&MethodRoutes>,
+    mut req: Request,
+    mut res: Response,
+) -> anyhow::Result<Response> {
     let res = match route_handler {
         Some(handler) => {
-            let method = handler.get(req.method.to_lowercase().as_str());
-            if let Some(len) = content_len {
-                let (body, _) = read_body(reader, len)
-                    .await
-                    .map_err(Error::InternalServerError)?;
-                req.body = body;
-            }
-            let res = Response::default();
-            match method {
-                Some(route_handler) => route_handler(req, res).await?.into(),
+            match find_method(handler, &req.method) {
+                Some(candidates) => {
+                    // the first candidate that all guards matched
+                    match candidates.iter().find(|r| r.reject(&req).is_none()) {
+                        Some(route) => {
+                            req.nest_path = route.nest_path.clone();
+                            if let Some(state) = &route.state {
+                                req.state = Some(state.clone());
+                            }
+                            // app and host router's middleware already run
+                            Next::new(route.middleware.iter().cloned(), route.handler.clone())
+                                .run(req, res)
+                                .await?
+                        }
+                        None => {
+                            res.status = candidates
+                                .iter()
+                                .find_map(|r| r.reject(&req))
+                                .map_or(Status::NotFound, |g| g.reject.clone());
+                            hosts.fallback(router, req, res).await
+                        }
+                    }
+                }
                 None => {
-                    let res = format!("HTTP/1.1 {}\r\n\r\n", Status::MethodNotAllowed);
-                    res.into_bytes()
+                    let allow = allow_methods(handler.keys());
+                    res.headers.insert("Allow", &allow)?;
+                    // answer OPTIONS automatically
+                    if req.method == Method::Options {
+                        return Ok(res);
+                    }
+                    res.status = Status::MethodNotAllowed;
+                    hosts.fallback(router, req, res).await
                 } // Method not allow
             }
         }
         None => {
-            drop_body(reader, content_len.map(|c| c.as_str())).await?;
-            format!("HTTP/1.1 {}\r\n\r\n", Status::NotFound).into_bytes()
+            res.status = Status::NotFound;
+            hosts.fallback(router, req, res).await
         } // 404
     };
     Ok(res)
 }
+
+/// Build `Allow` header value from registered methods
+///
+/// `HEAD` is implied by `GET` and `OPTIONS` is always allowed.
+#[inline]
+fn allow_methods<'a>(methods: impl Iterator<Item = &'a Method>) -> String {
+    let mut allow = methods.collect::<BTreeSet<_>>();
+    if allow.contains(&Method::Get) {
+        allow.insert(&Method::Head);
+    }
+    allow.insert(&Method::Options);
+    allow
+        .into_iter()
+        .map(|m| m.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{extract::Rejection, middleware::from_fn, multipart::Multipart};
+
+    fn request(head: &str) -> Request {
+        Request::parse_from_bytes(Bytes::from(format!("{head}\r\nHost: x\r\n\r\n"))).unwrap()
+    }
+
+    async fn mark(req: Request, res: Response, next: Next) -> anyhow::Result<Response> {
+        let mut res = next.run(req, res).await?;
+        res.headers.insert("x-layer", "app")?;
+        Ok(res)
+    }
+
+    #[tokio::test]
+    async fn fallbacks_keep_status_and_headers() {
+        let hosts = Arc::new(VirtualHosts {
+            default: Router::new()
+                .get("/", || async { "home" })
+                .not_found(|| async { "nothing here" })
+                .method_not_allowed(|method: Method| async move { format!("no {method}") }),
+            ..Default::default()
+        });
+
+        let res = dispatch(hosts.clone(), request("GET /missing HTTP/1.1"))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::NotFound);
+        assert_eq!(res.body, "nothing here");
+
+        let res = dispatch(hosts, request("DELETE / HTTP/1.1")).await.unwrap();
+        assert_eq!(res.status, Status::MethodNotAllowed);
+        assert_eq!(res.headers.get("allow"), Some("GET, HEAD, OPTIONS"));
+        assert_eq!(
+            res.headers.get("content-type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(res.body, "no DELETE");
+    }
+
+    #[tokio::test]
+    async fn app_middleware_wraps_every_response() {
+        let root = assets("layers");
+        let hosts = Arc::new(VirtualHosts {
+            default: Router::new()
+                .get("/", || async { "home" })
+                .assets("/static", &root)
+                .layer(from_fn("mark", mark)),
+            ..Default::default()
+        });
+
+        for (head, status) in [
+            ("GET / HTTP/1.1", Status::Ok),
+            ("GET /static/app.css HTTP/1.1", Status::Ok),
+            ("GET /missing HTTP/1.1", Status::NotFound),
+            ("POST / HTTP/1.1", Status::MethodNotAllowed),
+            ("OPTIONS / HTTP/1.1", Status::Ok),
+            ("OPTIONS * HTTP/1.1", Status::Ok),
+        ] {
+            let res = dispatch(hosts.clone(), request(head)).await.unwrap();
+            assert_eq!(res.status, status, "{head}");
+            assert_eq!(res.headers.get("x-layer"), Some("app"), "{head}");
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn head_keeps_length_except_no_content() {
+        let hosts = Arc::new(VirtualHosts {
+            default: Router::new()
+                .get("/", || async { "home" })
+                .get("/empty", || async { Status::NoContent }),
+            ..Default::default()
+        });
+        let res = dispatch(hosts.clone(), request("HEAD / HTTP/1.1"))
+            .await
+            .unwrap();
+        assert_eq!(res.headers.get("content-length"), Some("4"));
+        assert!(res.body.is_empty());
+
+        let res = dispatch(hosts, request("HEAD /empty HTTP/1.1"))
+            .await
+            .unwrap();
+        assert_eq!(res.status, Status::NoContent);
+        assert!(!res.headers.contains("content-length"));
+        assert!(!res.headers.contains("transfer-encoding"));
+    }
+
+    /// Request of `head` with it's body streamed in `chunks`
+    fn streaming(head: &str, chunks: &[&'static str]) -> Request {
+        let mut req = request(head);
+        let chunks = chunks.iter().map(|c| Ok(Bytes::from_static(c.as_bytes())));
+        req.set_body_stream(stream::iter(chunks.collect::<Vec<_>>()).boxed());
+        req
+    }
+
+    #[tokio::test]
+    async fn cap_buffered_body_and_stream_multipart() {
+        let hosts = Arc::new(VirtualHosts {
+            default: Router::new()
+                .post("/raw", |body: Bytes| async move { body.len().to_string() })
+                .post("/upload", |mut multipart: Multipart| async move {
+                    let mut size = 0;
+                    while let Some(field) = multipart.next_field().await? {
+                        size += field.bytes().await?.len();
+                    }
+                    Ok::<_, Rejection>(size.to_string())
+                })
+                .body_limit(8),
+            ..Default::default()
+        });
+
+        let res = dispatch(
+            hosts.clone(),
+            streaming("POST /raw HTTP/1.1", &["1234", "5678"]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.body, "8");
+        let res = dispatch(
+            hosts.clone(),
+            streaming("POST /raw HTTP/1.1", &["1234", "56789"]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status, Status::ContentTooLarge);
+        let head = "POST /raw HTTP/1.1\r\nContent-Length: 9";
+        let res = dispatch(hosts.clone(), streaming(head, &[])).await.unwrap();
+        assert_eq!(res.status, Status::ContentTooLarge);
+
+        let head = "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=x";
+        let body = "--x\r\nContent-Disposition: form-data; name=a\r\n\r\n0123456789\r\n--x--";
+        let res = dispatch(hosts, streaming(head, &[body])).await.unwrap();
+        assert_eq!(res.status, Status::Ok);
+        assert_eq!(res.body, "10");
+    }
+
+    /// `docs/index.html` and `app.css` under a fresh directory
+    fn assets(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rymo-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/index.html"), "docs").unwrap();
+        std::fs::write(root.join("app.css"), "body {}").unwrap();
+        root
+    }
+
+    #[tokio::test]
+    async fn strict_matches_only_canonical_paths() {
+        let root = assets("strict");
+        let hosts = Arc::new(VirtualHosts {
+            default: Router::new()
+                .get("/api", || async { "api" })
+                .assets("/static", &root)
+                .trailing_slash(TrailingSlash::Strict),
+            ..Default::default()
+        });
+        for (head, status) in [
+            ("GET /api HTTP/1.1", Status::Ok),
+            ("GET /api/ HTTP/1.1", Status::NotFound),
+            ("GET /static/docs/ HTTP/1.1", Status::Ok),
+            ("GET /static/docs HTTP/1.1", Status::NotFound),
+            ("GET /static/app.css HTTP/1.1", Status::Ok),
+            ("GET /static/app.css/ HTTP/1.1", Status::NotFound),
+        ] {
+            let res = dispatch(hosts.clone(), request(head)).await.unwrap();
+            assert_eq!(res.status, status, "{head}");
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn legacy_serves_directory_index_without_slash() {
+        let root = assets("legacy");
+        let resolve = |path| resolve_asset(TrailingSlash::Legacy, "/", &root, path);
+        let index = Asset::File(root.join("docs/index.html"));
+        assert_eq!(resolve("/docs").await, index);
+        assert_eq!(resolve("/docs/").await, index);
+        assert_eq!(resolve("/app.css").await, Asset::File(root.join("app.css")));
+        assert_eq!(resolve("/app.css/").await, Asset::NotFound);
+        assert_eq!(resolve("/missing").await, Asset::NotFound);
+        assert_eq!(resolve("/../etc/passwd").await, Asset::NotFound);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn redirect_to_canonical_asset_path() {
+        let root = assets("redirect");
+        let resolve = |path| resolve_asset(TrailingSlash::Redirect, "/static", &root, path);
+        assert_eq!(
+            resolve("/static/docs").await,
+            Asset::Redirect("/static/docs/".to_owned())
+        );
+        assert_eq!(
+            resolve("/static/app.css/").await,
+            Asset::Redirect("/static/app.css".to_owned())
+        );
+        assert_eq!(
+            resolve("/static/docs/").await,
+            Asset::File(root.join("docs/index.html"))
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
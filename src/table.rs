@@ -0,0 +1,256 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::{host::VirtualHosts, http::method::Method, middleware::BoxMiddleware, router::Router};
+
+/// A registered route or static assets mount
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RouteInfo {
+    /// Host pattern, `None` for the default host
+    pub host: Option<String>,
+    /// Route path pattern or static assets mount path
+    pub path: String,
+    /// `None` for static assets
+    pub method: Option<Method>,
+    pub name: Option<String>,
+    /// Directory of static assets
+    pub assets: Option<PathBuf>,
+    /// Count of guards
+    pub guards: usize,
+    /// Names of attached middleware, outermost first
+    pub middleware: Vec<String>,
+}
+
+/// Every registered route of the app
+///
+/// Use `{}` to pretty print it for startup logs, or [`RouteTable::to_json`]
+/// for tools.
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable(pub Vec<RouteInfo>);
+
+impl RouteTable {
+    /// Collect routes of every host, sorted by host, path and method
+    pub fn from_hosts(hosts: &VirtualHosts) -> Self {
+        let mut routes = Self::from_router(None, &hosts.default).0;
+        let app = middleware_names(&hosts.default.layers);
+        hosts
+            .exact
+            .iter()
+            .map(|(host, router)| (host.clone(), router))
+            .chain(
+                hosts
+                    .wildcard
+                    .iter()
+                    .map(|(suffix, router)| (format!("*{suffix}"), router)),
+            )
+            .for_each(|(host, router)| {
+                let host_routes = Self::from_router(Some(&host), router).0;
+                // app middleware runs around every response of the host
+                routes.extend(host_routes.into_iter().map(|mut r| {
+                    r.middleware.splice(0..0, app.iter().cloned());
+                    r
+                }));
+            });
+        routes.sort();
+        Self(routes)
+    }
+
+    /// Collect routes of `router`, sorted by path and method
+    ///
+    /// Static assets list the middleware of `router`, routes also list their
+    /// own.
+    pub fn from_router(host: Option<&str>, router: &Router) -> Self {
+        let host = host.map(|h| h.to_owned());
+        let layers = middleware_names(&router.layers);
+        let mut routes = router
+            .routes
+            .values()
+            .flat_map(|handlers| handlers.values().flatten())
+            .map(|route| RouteInfo {
+                host: host.clone(),
+                path: route.path.clone(),
+                method: Some(route.method.clone()),
+                name: route.name.clone(),
+                assets: None,
+                guards: route.guards.len(),
+                middleware: layers
+                    .iter()
+                    .cloned()
+                    .chain(middleware_names(&route.middleware))
+                    .collect(),
+            })
+            .chain(router.assets_routes.iter().map(|(path, assets)| RouteInfo {
+                host: host.clone(),
+                path: path.clone(),
+                method: None,
+                name: None,
+                assets: Some(assets.clone()),
+                guards: 0,
+                // static assets run inside the router's middleware only
+                middleware: layers.clone(),
+            }))
+            .collect::<Vec<_>>();
+        routes.sort();
+        Self(routes)
+    }
+
+    /// Dump routes as JSON array
+    ///
+    /// ```not_rust
+    /// [{"host":null,"method":"GET","path":"/","name":null,"assets":null,"guards":0,"middleware":[]}]
+    /// ```
+    pub fn to_json(&self) -> String {
+        let routes = self
+            .0
+            .iter()
+            .map(|r| {
+                let middleware = r
+                    .middleware
+                    .iter()
+                    .map(|m| json_string(m))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!(
+                    r#"{{"host":{},"method":{},"path":{},"name":{},"assets":{},"guards":{},"middleware":[{}]}}"#,
+                    json_option(r.host.as_deref()),
+                    json_option(r.method.as_ref().map(|m| m.as_str())),
+                    json_string(&r.path),
+                    json_option(r.name.as_deref()),
+                    json_option(r.assets.as_ref().map(|a| a.to_string_lossy()).as_deref()),
+                    r.guards,
+                    middleware,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("[{routes}]")
+    }
+}
+
+impl Display for RouteTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = self
+            .0
+            .iter()
+            .map(|r| {
+                let target = match &r.assets {
+                    Some(assets) => format!("assets {}", assets.display()),
+                    None if r.guards > 0 => format!("handler, {} guards", r.guards),
+                    None => "handler".to_owned(),
+                };
+                let middleware = if r.middleware.is_empty() {
+                    "-".to_owned()
+                } else {
+                    r.middleware.join(" > ")
+                };
+                [
+                    r.host.clone().unwrap_or("default".to_owned()),
+                    r.method
+                        .as_ref()
+                        .map_or("-".to_owned(), |m| m.as_str().to_owned()),
+                    r.path.clone(),
+                    r.name.clone().unwrap_or("-".to_owned()),
+                    middleware,
+                    target,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["HOST", "METHOD", "PATH", "NAME", "MIDDLEWARE", "TARGET"].map(String::from);
+        let widths: [usize; 6] = std::array::from_fn(|i| {
+            rows.iter()
+                .map(|r| r[i].len())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or(0)
+        });
+        for row in [header].iter().chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[inline]
+fn middleware_names(middleware: &[BoxMiddleware]) -> Vec<String> {
+    middleware.iter().map(|m| m.name().to_owned()).collect()
+}
+
+#[inline]
+fn json_option(value: Option<&str>) -> String {
+    value.map_or("null".to_owned(), json_string)
+}
+
+/// Quote and escape JSON string
+fn json_string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        middleware::{from_fn, Next},
+        request::Request,
+        response::Response,
+    };
+
+    async fn pass(req: Request, res: Response, next: Next) -> anyhow::Result<Response> {
+        next.run(req, res).await
+    }
+
+    #[test]
+    fn list_layers_of_assets_mounts() {
+        let mut hosts = VirtualHosts {
+            default: Router::new()
+                .get("/", || async { "" })
+                .assets("/static", Path::new("public"))
+                .layer(from_fn("app", pass)),
+            ..Default::default()
+        };
+        hosts
+            .add(
+                "api.example.com",
+                Router::new()
+                    .assets("/docs", Path::new("docs"))
+                    .layer(from_fn("host", pass)),
+            )
+            .unwrap();
+
+        let middleware = |host: Option<&str>, path: &str| {
+            RouteTable::from_hosts(&hosts)
+                .0
+                .into_iter()
+                .find(|r| r.host.as_deref() == host && r.path == path)
+                .unwrap()
+                .middleware
+        };
+        assert_eq!(middleware(None, "/"), ["app"]);
+        assert_eq!(middleware(None, "/static"), ["app"]);
+        assert_eq!(
+            middleware(Some("api.example.com"), "/docs"),
+            ["app", "host"]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use anyhow::anyhow;
+use bytes::{Buf, Bytes};
+use futures::{future::BoxFuture, StreamExt, TryStreamExt};
+use http_body::Body;
+use http_body_util::{BodyExt, Full};
+use tower::{Layer, Service, ServiceExt};
+
+use crate::{
+    error::{Error, Result},
+    handle::RouterHandle,
+    middleware::{Middleware, Next},
+    request::Request,
+    response::Response,
+    server::{dispatch, Rymo},
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Body of requests that rymo passes to tower services
+pub type ServiceBody = Full<Bytes>;
+
+/// The app as a tower service, see [`Rymo::service`]
+///
+/// Every call routes the request with routes at the moment, like a new
+/// connection does.
+#[derive(Clone)]
+pub struct RymoService {
+    hosts: RouterHandle,
+}
+
+impl Rymo<'_> {
+    /// Wrap the app as a tower service, it serves both rymo's and `http`
+    /// crate's requests
+    #[inline]
+    pub fn service(&self) -> RymoService {
+        RymoService {
+            hosts: self.handle(),
+        }
+    }
+}
+
+impl Service<Request> for RymoService {
+    type Response = Response;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let hosts = self.hosts.snapshot();
+        // a body read already is received by streaming routes as one chunk
+        Box::pin(async move { dispatch(hosts, req).await })
+    }
+}
+
+impl<B> Service<http::Request<B>> for RymoService
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Response = http::Response<ServiceBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let mut service = self.clone();
+        Box::pin(async move {
+            let req = from_http_stream(req).map_err(|e| Error::BadRequest(e.to_string()))?;
+            let res = service.call(req).await?;
+            Ok(to_http_response(res).await?)
+        })
+    }
+}
+
+/// Mount a tower service as route handler
+///
+/// ```not_rust
+/// app.get("/metrics", tower::service(metrics)).await;
+/// ```
+pub fn service<S, B>(
+    service: S,
+) -> impl Fn(Request, Response) -> BoxFuture<'static, anyhow::Result<Response>> + Send + Sync
+where
+    S: Service<http::Request<ServiceBody>, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    let service = Mutex::new(service);
+    move |req, _res| {
+        let service = clone_service(&service);
+        Box::pin(async move {
+            let res = service?
+                .oneshot(http::Request::try_from(req)?)
+                .await
+                .map_err(boxed)?;
+            from_http_response(res).await
+        })
+    }
+}
+
+/// Middleware built from a tower layer, see [`layer`]
+pub struct TowerLayer<S> {
+    name: String,
+    service: Mutex<S>,
+}
+
+/// Build middleware from a tower layer
+///
+/// The layer wraps [`NextService`], which runs the rest of middleware chain
+/// and the route handler. It's service is built once and cloned for every
+/// request, so layers like concurrency limit share their state.
+///
+/// ```not_rust
+/// app.layer(tower::layer("timeout", TimeoutLayer::new(Duration::from_secs(5)))).await;
+/// ```
+#[inline]
+pub fn layer<L>(name: &str, layer: L) -> TowerLayer<L::Service>
+where
+    L: Layer<NextService>,
+{
+    TowerLayer {
+        name: name.to_owned(),
+        service: Mutex::new(layer.layer(NextService)),
+    }
+}
+
+impl<S, B> Middleware for TowerLayer<S>
+where
+    S: Service<http::Request<ServiceBody>, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(
+        &self,
+        req: Request,
+        res: Response,
+        next: Next,
+    ) -> BoxFuture<'static, anyhow::Result<Response>> {
+        let service = clone_service(&self.service);
+        Box::pin(async move {
+            let mut req = http::Request::try_from(req)?;
+            req.extensions_mut()
+                .insert(Chain(Arc::new(Mutex::new(Some((next, res))))));
+            let res = service?.oneshot(req).await.map_err(boxed)?;
+            from_http_response(res).await
+        })
+    }
+}
+
+/// The rest of middleware chain, carried by request's extensions
+#[derive(Clone)]
+struct Chain(Arc<Mutex<Option<(Next, Response)>>>);
+
+/// Innermost service of a [`layer`], continue the middleware chain
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NextService;
+
+impl Service<http::Request<ServiceBody>> for NextService {
+    type Response = http::Response<ServiceBody>;
+    type Error = anyhow::Error;
+    type Future = BoxFuture<'static, anyhow::Result<Self::Response>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: http::Request<ServiceBody>) -> Self::Future {
+        let chain = req
+            .extensions_mut()
+            .remove::<Chain>()
+            .and_then(|chain| chain.0.lock().ok()?.take());
+        Box::pin(async move {
+            let (next, res) =
+                chain.ok_or_else(|| anyhow!("next service called outside of middleware"))?;
+            let req = from_http_request(req).await?;
+            let res = next.run(req, res).await?;
+            to_http_response(res).await
+        })
+    }
+}
+
+#[inline]
+fn clone_service<S: Clone>(service: &Mutex<S>) -> anyhow::Result<S> {
+    service
+        .lock()
+        .map(|s| s.clone())
+        .map_err(|e| anyhow!("tower service poisoned {e}"))
+}
+
+#[inline]
+fn boxed(err: impl Into<BoxError>) -> anyhow::Error {
+    let err: BoxError = err.into();
+    anyhow!(err)
+}
+
+/// Streaming body is collected, `http` bodies here are full
+async fn to_http_response(mut res: Response) -> anyhow::Result<http::Response<ServiceBody>> {
+    if let Some(stream) = res.stream.take() {
+        let chunks = stream.try_collect::<Vec<_>>().await?;
+        res.body = chunks.concat().into();
+    }
+    http::Response::try_from(res)
+}
+
+/// The body is streamed, it's read up to [`Request::body_limit`] like the
+/// body of a connection
+fn from_http_stream<B>(req: http::Request<B>) -> anyhow::Result<Request>
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = req.into_parts();
+    let mut req = Request::try_from(http::Request::from_parts(parts, Bytes::new()))?;
+    let stream = body
+        .into_data_stream()
+        .map_ok(|mut data| data.copy_to_bytes(data.remaining()))
+        .map_err(boxed);
+    req.set_body_stream(stream.boxed());
+    Ok(req)
+}
+
+/// Body of middleware requests, it's read already before the middleware
+async fn from_http_request<B>(req: http::Request<B>) -> anyhow::Result<Request>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await.map_err(boxed)?.to_bytes();
+    Request::try_from(http::Request::from_parts(parts, body))
+}
+
+async fn from_http_response<B>(res: http::Response<B>) -> anyhow::Result<Response>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = res.into_parts();
+    let body = body.collect().await.map_err(boxed)?.to_bytes();
+    Response::try_from(http::Response::from_parts(parts, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cap_http_request_body() {
+        let app = Rymo::new("0");
+        app.body_limit(4).await;
+        app.post("/", |body: Bytes| async move { body.len().to_string() })
+            .await;
+        let call = |body: &'static str, length: bool| {
+            let mut req = http::Request::post("/").header("host", "x");
+            if length {
+                req = req.header("content-length", body.len());
+            }
+            let req = req.body(Full::new(Bytes::from(body))).unwrap();
+            app.service().oneshot(req)
+        };
+
+        let res = call("1234", true).await.unwrap();
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(res.into_body().collect().await.unwrap().to_bytes(), "4");
+        for length in [true, false] {
+            let res = call("12345", length).await.unwrap();
+            assert_eq!(res.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, bail, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Characters that must be encoded in a path segment
+///
+/// https://url.spec.whatwg.org/#path-percent-encode-set
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+/// Wildcard parameter keeps it's `/`
+const WILDCARD: &AsciiSet = &SEGMENT.remove(b'/');
+/// Characters that must be encoded in a query key or value
+const QUERY: &AsciiSet = &SEGMENT.add(b'&').add(b'=').add(b'+').add(b'\'');
+
+/// Build URL of named routes
+///
+/// Every request carries the builder of the router that served it,
+/// see [`crate::request::Request::url_for`].
+#[derive(Default, Clone)]
+pub struct UrlBuilder {
+    /// route_name : path_pattern
+    names: Arc<HashMap<String, String>>,
+}
+
+impl UrlBuilder {
+    /// Path pattern of the named route
+    #[inline]
+    pub fn pattern(&self, name: &str) -> Option<&str> {
+        self.names.get(name).map(|p| p.as_str())
+    }
+
+    /// Fill path parameters and append query
+    ///
+    /// ## Arguments
+    ///
+    /// - `name`: the route's name
+    /// - `params`: value of `:param` and `*wildcard` segments
+    /// - `query`: query pairs, nothing appended when empty
+    pub fn url_for(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String> {
+        let pattern = self
+            .pattern(name)
+            .ok_or_else(|| anyhow!("route {name} not found"))?;
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| anyhow!("missing parameter {key} for route {name}"))
+        };
+
+        let mut url = pattern
+            .split('/')
+            .map(|segment| {
+                if let Some(key) = segment.strip_prefix(':') {
+                    let value = param(key)?;
+                    if value.is_empty() {
+                        bail!("empty parameter {key} for route {name}");
+                    }
+                    Ok(utf8_percent_encode(value, SEGMENT).to_string())
+                } else if let Some(key) = segment.strip_prefix('*') {
+                    Ok(utf8_percent_encode(param(key)?, WILDCARD).to_string())
+                } else {
+                    Ok(segment.to_owned())
+                }
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("/");
+
+        if !query.is_empty() {
+            let query = query
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "{}={}",
+                        utf8_percent_encode(k, QUERY),
+                        utf8_percent_encode(v, QUERY)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            url.push('?');
+            url.push_str(&query);
+        }
+        Ok(url)
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    #[inline]
+    pub(crate) fn remove(&mut self, name: &str) {
+        Arc::make_mut(&mut self.names).remove(name);
+    }
+
+    /// Fail when the name is already registered
+    #[inline]
+    pub(crate) fn insert(&mut self, name: &str, pattern: &str) -> Result<()> {
+        if self.contains(name) {
+            bail!("route name {name} already registered");
+        }
+        Arc::make_mut(&mut self.names).insert(name.to_owned(), pattern.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> UrlBuilder {
+        let mut urls = UrlBuilder::default();
+        urls.insert("user", "/users/:id").unwrap();
+        urls.insert("file", "/files/*path").unwrap();
+        urls
+    }
+
+    #[test]
+    fn reject_duplicate_names() {
+        let mut urls = builder();
+        assert!(urls.insert("user", "/members/:id").is_err());
+        assert_eq!(urls.pattern("user"), Some("/users/:id"));
+        urls.remove("user");
+        assert!(urls.insert("user", "/members/:id").is_ok());
+    }
+
+    #[test]
+    fn fill_params_and_query() {
+        let urls = builder();
+        assert_eq!(
+            urls.url_for("user", &[("id", "a b/c")], &[("q", "x&y=z")])
+                .unwrap(),
+            "/users/a%20b%2Fc?q=x%26y%3Dz"
+        );
+        assert_eq!(
+            urls.url_for("file", &[("path", "a/b c.txt")], &[]).unwrap(),
+            "/files/a/b%20c.txt"
+        );
+    }
+
+    #[test]
+    fn reject_missing_params() {
+        let urls = builder();
+        assert!(urls.url_for("missing", &[], &[]).is_err());
+        assert!(urls.url_for("user", &[], &[]).is_err());
+        assert!(urls.url_for("user", &[("id", "")], &[]).is_err());
+    }
+}